@@ -0,0 +1,21 @@
+use alco::{count, run_with_input, runner, ParamBuilder, ParamSamples, ReverseSortedVec};
+
+fn sort(params: ParamSamples) {
+    // building the input scales with `n` as well, it is generated outside of the measurement
+    run_with_input(&params, &ReverseSortedVec::new("n"), |data| {
+        data.sort_by(|a, b| {
+            count("comparisons", 1);
+            a.cmp(b)
+        })
+    })
+    .unwrap();
+}
+
+fn main() {
+    let mut params = ParamBuilder::new();
+
+    params.add_log_range("n", 10..100_000, 20).unwrap();
+    params.manual_instrumentation();
+
+    runner(&[&("sort", sort, params)]);
+}
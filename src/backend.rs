@@ -0,0 +1,49 @@
+use crate::metric::Metric;
+use crate::valgrind::ValgrindConfig;
+
+/// Measurement backend used to explore the parameter space of a benchmark
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// Count instructions and simulate caches with cachegrind
+    Cachegrind,
+    /// Measure wall-clock time in-process, works without valgrind
+    WallClock,
+    /// Read hardware counters in-process with `perf_event_open`, requires the `perf` feature
+    Perf,
+}
+
+impl Backend {
+    pub fn from_str(name: &str) -> Option<Self> {
+        match name {
+            "cachegrind" => Some(Backend::Cachegrind),
+            "wallclock" => Some(Backend::WallClock),
+            "perf" => Some(Backend::Perf),
+            _ => None,
+        }
+    }
+
+    /// Metric which is estimated if a benchmark does not select any supported metric
+    pub fn default_metric(&self) -> Metric {
+        match self {
+            Backend::Cachegrind => Metric::InstructionReads,
+            Backend::WallClock => Metric::WallTime,
+            Backend::Perf => Metric::InstructionReads,
+        }
+    }
+
+    /// In-process backends measure in the runner itself, parallel runs would disturb each other
+    pub fn in_process(&self) -> bool {
+        matches!(self, Backend::WallClock | Backend::Perf)
+    }
+
+    pub fn supports(&self, metric: Metric, config: Option<&ValgrindConfig>) -> bool {
+        match self {
+            Backend::Cachegrind => {
+                let cache_sim = config.map(|x| x.cache_sim).unwrap_or(false);
+                metric != Metric::WallTime && metric != Metric::Cycles && (cache_sim || !metric.requires_cache_sim())
+            }
+            Backend::Perf => matches!(metric, Metric::InstructionReads | Metric::Cycles | Metric::LLMisses),
+            Backend::WallClock => metric == Metric::WallTime,
+        }
+    }
+}
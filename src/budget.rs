@@ -0,0 +1,84 @@
+//! Limits on the time and instructions spent on a single benchmark
+//!
+//! Valgrind cannot stop a run after a number of instructions, so the remaining instruction budget
+//! is converted to a timeout using the rate at which previous runs executed instructions.
+
+use std::time::Duration;
+
+use crate::metric::{Metric, Stats};
+
+/// Instruction budget and time limit of a benchmark
+#[derive(Clone, Debug)]
+pub struct Budget {
+    /// Time limit of a single run
+    timeout: Duration,
+    /// Total number of instructions of all runs, unlimited if `None`
+    instructions: Option<u64>,
+    /// Instructions executed so far
+    spent: u64,
+    /// Time spent in runs which reported their instructions
+    elapsed: Duration,
+}
+
+impl Budget {
+    pub fn new(timeout: Duration, instructions: Option<u64>) -> Self {
+        Budget {
+            timeout,
+            instructions,
+            spent: 0,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Account for a finished run
+    pub fn record(&mut self, stats: &Stats, elapsed: Duration) {
+        if let Some(instructions) = stats.get(Metric::InstructionReads) {
+            self.spent += instructions;
+            self.elapsed += elapsed;
+        }
+    }
+
+    /// Account for a run that was stopped early, assuming it executed instructions at the rate of
+    /// previous runs
+    pub fn record_stopped(&mut self, elapsed: Duration) {
+        if self.spent > 0 && !self.elapsed.is_zero() {
+            let rate = self.spent as f64 / self.elapsed.as_secs_f64();
+
+            self.spent += (rate * elapsed.as_secs_f64()) as u64;
+            self.elapsed += elapsed;
+        }
+    }
+
+    /// Costs a run stopped after `elapsed` had at least, the instructions executed at the rate of
+    /// previous runs
+    pub fn lower_bound(&self, elapsed: Duration) -> Stats {
+        let mut stats = Stats::default();
+
+        if self.spent > 0 && !self.elapsed.is_zero() {
+            let rate = self.spent as f64 / self.elapsed.as_secs_f64();
+            stats.insert(Metric::InstructionReads, (rate * elapsed.as_secs_f64()) as u64);
+        }
+
+        stats
+    }
+
+    pub fn exhausted(&self) -> bool {
+        matches!(self.instructions, Some(instructions) if self.spent >= instructions)
+    }
+
+    /// Time after which the next `concurrent` runs are stopped
+    ///
+    /// This is the time limit of a single run, or less if the runs would exceed the remaining
+    /// instructions at the rate of previous runs.
+    pub fn deadline(&self, concurrent: usize) -> Duration {
+        let instructions = match self.instructions {
+            Some(instructions) if self.spent > 0 && !self.elapsed.is_zero() => instructions,
+            _ => return self.timeout,
+        };
+
+        let rate = self.spent as f64 / self.elapsed.as_secs_f64();
+        let remaining = instructions.saturating_sub(self.spent) as f64 / (rate * concurrent.max(1) as f64);
+
+        self.timeout.min(Duration::from_secs_f64(remaining))
+    }
+}
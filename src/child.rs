@@ -0,0 +1,165 @@
+//! Helpers to run a benchmark in a child process
+
+use std::{
+    ffi::OsStr,
+    fs::File,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::error::{Error, Result};
+use crate::params::ParamSamples;
+
+/// Number of trailing log lines reported if the child exits without a panic message
+const TAIL_LINES: usize = 10;
+
+/// Interval in which a running child is checked for its timeout
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Timeout of a single child process in milliseconds, zero disables the timeout
+static TIMEOUT: AtomicU64 = AtomicU64::new(0);
+
+/// Kill child processes running longer than `timeout`
+///
+/// The runner lowers the timeout if the instruction budget of a benchmark is nearly spent.
+pub fn set_timeout(timeout: Option<Duration>) {
+    let millis = timeout.map(|x| (x.as_millis() as u64).max(1)).unwrap_or(0);
+
+    TIMEOUT.store(millis, Ordering::Relaxed);
+}
+
+fn timeout() -> Option<Duration> {
+    match TIMEOUT.load(Ordering::Relaxed) {
+        0 => None,
+        millis => Some(Duration::from_millis(millis)),
+    }
+}
+
+/// Append the arguments to run benchmark `i` with `params` in the child process
+pub fn child_args<'a>(cmd: &'a mut Command, i: usize, params: &ParamSamples) -> &'a mut Command {
+    cmd.arg("--alco-run")
+        .arg(i.to_string())
+        .arg(params.is_setup().to_string())
+        .arg(params.seed().to_string());
+
+    for arg in params.to_args() {
+        cmd.arg(arg);
+    }
+
+    cmd
+}
+
+/// Name of a sample point usable in file names, e.g. `n=100_k=3`, `setup_n=3` or `seed=2_n=100`
+///
+/// Runs of different sample points write to different files and can be executed in parallel.
+pub fn sample_name(params: &ParamSamples) -> String {
+    let seed = match params.seed() {
+        0 => String::new(),
+        seed => format!("seed={}_", seed),
+    };
+
+    format!("{}{}{}", if params.is_setup() { "setup_" } else { "" }, seed, params)
+        .chars()
+        .map(|c| if c.is_alphanumeric() || "=.-_".contains(c) { c } else { '_' })
+        .collect()
+}
+
+/// Log file of a sample point, e.g. `target/alco/logs/sort/cachegrind_n=100.log`
+fn log_file(tool: &str, name: &str, params: &ParamSamples) -> PathBuf {
+    PathBuf::from(format!("target/alco/logs/{}/{}_{}.log", name, tool, sample_name(params)))
+}
+
+/// Quote an argument for the shell, if necessary
+fn quote(arg: &OsStr) -> String {
+    let arg = arg.to_string_lossy();
+    if !arg.is_empty() && arg.chars().all(|c| c.is_alphanumeric() || "-_=./,:§".contains(c)) {
+        arg.into_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Command line to reproduce a run of the child process
+fn command_line(cmd: &Command) -> String {
+    let envs = cmd.get_envs()
+        .filter_map(|(key, value)| Some(format!("{}={}", key.to_string_lossy(), quote(value?))));
+    let args = std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(quote);
+
+    envs.chain(args).collect::<Vec<_>>().join(" ")
+}
+
+/// Extract the panic message from the output of a child process, or the last lines of the output
+/// if the child did not panic
+///
+/// Lines written by valgrind itself, prefixed with `==pid==`, are skipped.
+fn crash_message(log: &str) -> String {
+    let lines = log.lines()
+        .filter(|line| !line.starts_with("=="))
+        .collect::<Vec<_>>();
+
+    if let Some(start) = lines.iter().position(|line| line.contains("panicked at")) {
+        return lines[start..].iter()
+            .take_while(|line| !line.starts_with("note:") && !line.starts_with("stack backtrace:"))
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    lines[lines.len().saturating_sub(TAIL_LINES)..].join("\n")
+}
+
+/// Run the child process with `params` of benchmark `name`
+///
+/// The output of the child is written to a log file under `target/alco/logs/<name>`. If the child
+/// fails, the returned error contains its panic message and a command line to reproduce the run.
+/// A child running longer than the timeout is killed and `Error::Timeout` returned.
+pub fn run_child(cmd: &mut Command, tool: &str, name: &str, params: &ParamSamples) -> Result<()> {
+    let log = log_file(tool, name, params);
+    std::fs::create_dir_all(log.parent().unwrap())?;
+
+    let stdout = File::create(&log)?;
+    let stderr = stdout.try_clone()?;
+
+    let mut child = cmd
+        .stdout(Stdio::from(stdout))
+        .stderr(Stdio::from(stderr))
+        .spawn()
+        .map_err(|e| Error::Launch(tool.to_string(), e))?;
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        if let Some(timeout) = timeout() {
+            if start.elapsed() > timeout {
+                child.kill()?;
+                child.wait()?;
+
+                return Err(Error::Timeout(tool.to_string(), timeout));
+            }
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    if !status.success() {
+        let output = std::fs::read(&log)?;
+
+        return Err(Error::ChildCrashed {
+            tool: tool.to_string(),
+            status,
+            params: format!("{}", params),
+            message: crash_message(&String::from_utf8_lossy(&output)),
+            log,
+            command: command_line(cmd),
+        });
+    }
+
+    Ok(())
+}
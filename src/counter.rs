@@ -0,0 +1,157 @@
+//! User-defined operation counters, e.g. the number of comparisons of a sorting algorithm
+//!
+//! The counters are accumulated in the child process and reported to the parent through the file
+//! in `ALCO_COUNTER_FILE`. They are measured natively, independent of valgrind. In all other runs
+//! `count` returns right away, so that counting does not inflate the measured costs.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::PathBuf,
+    process::Command,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Mutex,
+    },
+};
+
+use crate::child::{child_args, run_child, sample_name};
+use crate::error::{Error, Result};
+use crate::params::ParamSamples;
+
+/// Counters of finished threads, merged with the ones of the current thread when reporting
+static COUNTERS: Mutex<Vec<(&'static str, u64)>> = Mutex::new(Vec::new());
+
+/// Whether `ALCO_COUNTER_FILE` is set, cached on the first call of `count`
+static STATE: AtomicU8 = AtomicU8::new(UNKNOWN);
+
+const UNKNOWN: u8 = 0;
+const DISABLED: u8 = 1;
+const ENABLED: u8 = 2;
+
+/// Counters of a single thread, flushed into `COUNTERS` when the thread exits
+struct LocalCounters(Vec<(&'static str, u64)>);
+
+impl LocalCounters {
+    fn add(&mut self, name: &'static str, value: u64) {
+        // counter names are usually literals, compare their address before the content
+        match self.0.iter_mut().find(|x| std::ptr::eq(x.0, name) || x.0 == name) {
+            Some(counter) => counter.1 += value,
+            None => self.0.push((name, value)),
+        }
+    }
+
+    fn flush(&mut self) {
+        let mut counters = COUNTERS.lock().unwrap();
+
+        for (name, value) in self.0.drain(..) {
+            match counters.iter_mut().find(|x| x.0 == name) {
+                Some(counter) => counter.1 += value,
+                None => counters.push((name, value)),
+            }
+        }
+    }
+}
+
+impl Drop for LocalCounters {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+thread_local! {
+    static LOCAL: RefCell<LocalCounters> = const { RefCell::new(LocalCounters(Vec::new())) };
+}
+
+fn enabled() -> bool {
+    match STATE.load(Ordering::Relaxed) {
+        UNKNOWN => {
+            let enabled = std::env::var_os("ALCO_COUNTER_FILE").is_some();
+            STATE.store(if enabled { ENABLED } else { DISABLED }, Ordering::Relaxed);
+
+            enabled
+        }
+        state => state == ENABLED,
+    }
+}
+
+/// Add `value` to the counter `name`
+///
+/// Each counter is reported to the runner and its complexity estimated separately. Counters are
+/// reset by `start_instrumentation`.
+pub fn count(name: &'static str, value: u64) {
+    if !enabled() {
+        return;
+    }
+
+    LOCAL.with(|x| x.borrow_mut().add(name, value));
+}
+
+/// Reset all counters to zero
+pub fn reset() {
+    if !enabled() {
+        return;
+    }
+
+    LOCAL.with(|x| x.borrow_mut().0.clear());
+    COUNTERS.lock().unwrap().clear();
+}
+
+fn counter_file(name: &str, params: &ParamSamples) -> PathBuf {
+    PathBuf::from(format!("target/alco/counter.out.{}.{}", name, sample_name(params)))
+}
+
+/// Write all counters to the side channel of the parent, if requested
+pub fn write_report() {
+    let file = match std::env::var_os("ALCO_COUNTER_FILE") {
+        Some(file) => file,
+        None => return,
+    };
+
+    LOCAL.with(|x| x.borrow_mut().flush());
+
+    let report = COUNTERS.lock().unwrap().iter()
+        .map(|(name, value)| format!("{}§{}\n", name, value))
+        .collect::<String>();
+
+    std::fs::write(file, report)
+        .expect("Unable to write counter report");
+}
+
+fn read_report(file: &PathBuf) -> Result<HashMap<String, u64>> {
+    let content = match std::fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(_) => return Ok(HashMap::new()),
+    };
+
+    content.lines()
+        .map(|line| {
+            let mut parts = line.rsplitn(2, '§');
+            let value = parts.next().and_then(|x| x.parse().ok());
+            match (parts.next(), value) {
+                (Some(name), Some(value)) => Ok((name.to_string(), value)),
+                _ => Err(Error::ParseOutput("counter report".to_string(), line.to_string())),
+            }
+        })
+        .collect()
+}
+
+/// Run benchmark `i` natively and return the value of every counter
+pub fn measure_counters(
+    executable: &str,
+    i: usize,
+    params: &ParamSamples,
+    name: &str,
+) -> Result<HashMap<String, u64>> {
+    let file = counter_file(name, params);
+    std::fs::create_dir_all(file.parent().unwrap())?;
+
+    if file.exists() {
+        std::fs::remove_file(&file)?;
+    }
+
+    let mut cmd = Command::new(executable);
+    run_child(child_args(&mut cmd, i, params).env("ALCO_COUNTER_FILE", &file), "native", name, params)?;
+
+    read_report(&file)
+}
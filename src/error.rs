@@ -1,5 +1,9 @@
 use thiserror::Error;
+use std::io;
 use std::num::{ParseIntError, ParseFloatError};
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::time::Duration;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -11,10 +15,41 @@ pub enum Error {
     InvalidType(String),
     #[error("The argument {0} already exists")]
     ArgumentAlreadyExists(String),
+    #[error("The argument {0} does not exist")]
+    UnknownArgument(String),
+    #[error("Unknown input distribution {0}, generate it in the benchmark")]
+    UnknownDistribution(String),
+    #[error("The range of argument {0} is empty or starts at zero")]
+    InvalidRange(String),
+    #[error("Cache size {0} is not a power of two")]
+    InvalidCacheSize(usize),
+    #[error("The lower bound of the arguments violates a constraint")]
+    InvalidLowerBound,
+    #[error("Step {1} is out of range for argument {0}")]
+    StepOutOfRange(String, usize),
     #[error("Could not parse the argument type string {0}")]
     InvalidParamTypeStr(String),
     #[error("Parsing integer failed")]
     ParseInt(#[from] ParseIntError),
     #[error("Parsing float failed")]
     ParseFloat(#[from] ParseFloatError),
+    #[error("Failed to launch {0}: {1}")]
+    Launch(String, #[source] io::Error),
+    #[error("Benchmark failed in {tool} with {status} at {params}\n{message}\n\nOutput written to {}\nReproduce with: {command}", log.display())]
+    ChildCrashed {
+        tool: String,
+        status: ExitStatus,
+        params: String,
+        message: String,
+        log: PathBuf,
+        command: String,
+    },
+    #[error("Benchmark in {0} exceeded its time limit of {1:?}")]
+    Timeout(String, Duration),
+    #[error("Unable to parse {0} output: {1}")]
+    ParseOutput(String, String),
+    #[error("Hardware counters are not available")]
+    CountersUnavailable,
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
 }
@@ -0,0 +1,201 @@
+//! Generators building the input of a benchmark from its parameters
+//!
+//! The input is generated before `start_instrumentation` and dropped after
+//! `stop_instrumentation`, so building it never contaminates the measured costs. Benchmarks using
+//! `run_with_input` have to enable `ParamBuilder::manual_instrumentation`. Random inputs are drawn
+//! from `ParamSamples::rng`, so every run of a repetition sees the same input.
+//!
+//! ```ignore
+//! fn sort(params: ParamSamples) {
+//!     alco::run_with_input(&params, &alco::RandomVec::new("n"), |data| data.sort()).unwrap();
+//! }
+//! ```
+
+use rand::{distributions::Alphanumeric, Rng};
+
+use crate::error::{Error, Result};
+use crate::instrumentation::{self, start_instrumentation, stop_instrumentation};
+use crate::params::ParamSamples;
+use crate::black_box;
+
+/// Maps a sample point to a concrete input of a benchmark
+pub trait Input {
+    type Output;
+
+    fn generate(&self, params: &ParamSamples) -> Result<Self::Output>;
+}
+
+/// Generate the input for `params`, then measure only `func` applied to it
+///
+/// Fails without measuring anything if the input cannot be generated. Panics if the benchmark is not
+/// registered with `ParamBuilder::manual_instrumentation`, since the generation of the input would be
+/// measured otherwise.
+pub fn run_with_input<I: Input, R>(params: &ParamSamples, input: &I, func: impl FnOnce(&mut I::Output) -> R) -> Result<()> {
+    assert!(
+        instrumentation::is_manual(),
+        "run_with_input requires ParamBuilder::manual_instrumentation, otherwise the input generation is measured"
+    );
+
+    let mut input = input.generate(params)?;
+
+    start_instrumentation();
+    let result = func(&mut input);
+    stop_instrumentation();
+
+    black_box(result);
+
+    Ok(())
+}
+
+/// Read the length of an input from the integer parameter `name`
+fn length(params: &ParamSamples, name: &str) -> Result<usize> {
+    params.get_usize(name).ok_or_else(|| Error::UnknownArgument(name.to_string()))
+}
+
+/// Vector of uniformly random integers with length given by a parameter
+pub struct RandomVec(&'static str);
+
+impl RandomVec {
+    pub fn new(name: &'static str) -> Self {
+        RandomVec(name)
+    }
+}
+
+impl Input for RandomVec {
+    type Output = Vec<u64>;
+
+    fn generate(&self, params: &ParamSamples) -> Result<Vec<u64>> {
+        let mut rng = params.rng();
+
+        Ok((0..length(params, self.0)?).map(|_| rng.gen()).collect())
+    }
+}
+
+/// Vector `0, 1, ..., n - 1` with length given by a parameter
+pub struct SortedVec(&'static str);
+
+impl SortedVec {
+    pub fn new(name: &'static str) -> Self {
+        SortedVec(name)
+    }
+}
+
+impl Input for SortedVec {
+    type Output = Vec<u64>;
+
+    fn generate(&self, params: &ParamSamples) -> Result<Vec<u64>> {
+        Ok((0..length(params, self.0)? as u64).collect())
+    }
+}
+
+/// Vector `n - 1, ..., 1, 0` with length given by a parameter
+pub struct ReverseSortedVec(&'static str);
+
+impl ReverseSortedVec {
+    pub fn new(name: &'static str) -> Self {
+        ReverseSortedVec(name)
+    }
+}
+
+impl Input for ReverseSortedVec {
+    type Output = Vec<u64>;
+
+    fn generate(&self, params: &ParamSamples) -> Result<Vec<u64>> {
+        Ok((0..length(params, self.0)? as u64).rev().collect())
+    }
+}
+
+/// Vector with length given by a parameter, ordered by the input distribution of the sample point
+///
+/// Supports the distributions `random`, `sorted` and `reversed`, see
+/// `ParamBuilder::add_distributions`. Without a distribution the vector is random, other
+/// distributions fail with `Error::UnknownDistribution`.
+pub struct DistributedVec(&'static str);
+
+impl DistributedVec {
+    pub fn new(name: &'static str) -> Self {
+        DistributedVec(name)
+    }
+}
+
+impl Input for DistributedVec {
+    type Output = Vec<u64>;
+
+    fn generate(&self, params: &ParamSamples) -> Result<Vec<u64>> {
+        match params.distribution() {
+            None | Some("random") => RandomVec(self.0).generate(params),
+            Some("sorted") => SortedVec(self.0).generate(params),
+            Some("reversed") => ReverseSortedVec(self.0).generate(params),
+            Some(distribution) => Err(Error::UnknownDistribution(distribution.to_string())),
+        }
+    }
+}
+
+/// Random alphanumeric string with length given by a parameter
+pub struct RandomString(&'static str);
+
+impl RandomString {
+    pub fn new(name: &'static str) -> Self {
+        RandomString(name)
+    }
+}
+
+impl Input for RandomString {
+    type Output = String;
+
+    fn generate(&self, params: &ParamSamples) -> Result<String> {
+        let length = length(params, self.0)?;
+
+        Ok(params.rng().sample_iter(&Alphanumeric)
+            .take(length)
+            .map(char::from)
+            .collect())
+    }
+}
+
+/// Directed graph as edge list
+#[derive(Clone, Debug)]
+pub struct Graph {
+    pub num_vertices: usize,
+    pub edges: Vec<(usize, usize)>,
+}
+
+/// Random graph with the number of vertices and edges given by two parameters
+///
+/// The edges are drawn uniformly without self loops, but may contain duplicates.
+pub struct RandomGraph {
+    vertices: &'static str,
+    edges: &'static str,
+}
+
+impl RandomGraph {
+    pub fn new(vertices: &'static str, edges: &'static str) -> Self {
+        RandomGraph { vertices, edges }
+    }
+}
+
+impl Input for RandomGraph {
+    type Output = Graph;
+
+    fn generate(&self, params: &ParamSamples) -> Result<Graph> {
+        let num_vertices = length(params, self.vertices)?;
+        let num_edges = length(params, self.edges)?;
+        let mut rng = params.rng();
+
+        let edges = if num_vertices < 2 {
+            Vec::new()
+        } else {
+            (0..num_edges)
+                .map(|_| {
+                    let from = rng.gen_range(0..num_vertices);
+                    // draw from the remaining vertices to avoid self loops
+                    let to = (from + rng.gen_range(1..num_vertices)) % num_vertices;
+
+                    (from, to)
+                })
+                .collect()
+        };
+
+        Ok(Graph { num_vertices, edges })
+    }
+}
@@ -0,0 +1,122 @@
+//! Valgrind client requests to toggle instrumentation from within a benchmark
+//!
+//! The requests are encoded with the "magic" instruction sequence of `valgrind.h`. It is a no-op
+//! when running natively and interpreted by valgrind otherwise.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::{counter, memory, perf, wallclock};
+
+/// Equivalent to `VG_USERREQ_TOOL_BASE('C', 'G')` in `cachegrind.h`
+const CACHEGRIND_BASE: usize = ((b'C' as usize) << 24) | ((b'G' as usize) << 16);
+
+const CACHEGRIND_START_INSTRUMENTATION: usize = CACHEGRIND_BASE;
+const CACHEGRIND_STOP_INSTRUMENTATION: usize = CACHEGRIND_BASE + 1;
+
+/// Equivalent to `VG_USERREQ_TOOL_BASE('C', 'T')` in `callgrind.h`
+const CALLGRIND_BASE: usize = ((b'C' as usize) << 24) | ((b'T' as usize) << 16);
+
+const CALLGRIND_START_INSTRUMENTATION: usize = CALLGRIND_BASE + 4;
+const CALLGRIND_STOP_INSTRUMENTATION: usize = CALLGRIND_BASE + 5;
+
+/// Stop request of the current tool, resolved in `start_instrumentation` so that looking up the
+/// tool is not counted
+static STOP_REQUEST: AtomicUsize = AtomicUsize::new(CACHEGRIND_STOP_INSTRUMENTATION);
+
+/// Whether the running benchmark is registered with `ParamBuilder::manual_instrumentation`
+static MANUAL: AtomicBool = AtomicBool::new(false);
+
+/// Set by the runner before a benchmark is executed
+pub(crate) fn set_manual(manual: bool) {
+    MANUAL.store(manual, Ordering::Relaxed);
+}
+
+pub(crate) fn is_manual() -> bool {
+    MANUAL.load(Ordering::Relaxed)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+fn client_request(default: usize, request: usize, args: [usize; 5]) -> usize {
+    let data = [request, args[0], args[1], args[2], args[3], args[4]];
+    let result;
+
+    unsafe {
+        std::arch::asm!(
+            "rol rdi, 3",
+            "rol rdi, 13",
+            "rol rdi, 61",
+            "rol rdi, 51",
+            "xchg rbx, rbx",
+            in("rax") data.as_ptr(),
+            inout("rdx") default => result,
+            options(nostack),
+        );
+    }
+
+    result
+}
+
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn client_request(default: usize, request: usize, args: [usize; 5]) -> usize {
+    let data = [request, args[0], args[1], args[2], args[3], args[4]];
+    let result;
+
+    unsafe {
+        std::arch::asm!(
+            "ror x12, x12, #3",
+            "ror x12, x12, #13",
+            "ror x12, x12, #51",
+            "ror x12, x12, #61",
+            "orr x10, x10, x10",
+            in("x4") data.as_ptr(),
+            inout("x3") default => result,
+            options(nostack),
+        );
+    }
+
+    result
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+#[inline(always)]
+fn client_request(default: usize, _request: usize, _args: [usize; 5]) -> usize {
+    default
+}
+
+/// Returns the start and stop requests of the valgrind tool the benchmark runs in
+fn tool_requests() -> (usize, usize) {
+    match std::env::var("ALCO_TOOL").as_deref() {
+        Ok("callgrind") => (CALLGRIND_START_INSTRUMENTATION, CALLGRIND_STOP_INSTRUMENTATION),
+        _ => (CACHEGRIND_START_INSTRUMENTATION, CACHEGRIND_STOP_INSTRUMENTATION),
+    }
+}
+
+/// Start counting events in the benchmark
+///
+/// If a benchmark is registered with `ParamBuilder::manual_instrumentation` nothing is counted
+/// until this function is called. Use it to exclude the setup of the input, for example building
+/// a vector of size `n`, from the measurement. This also resets the heap usage counted by
+/// `CountingAllocator` and user-defined counters, and starts the timer and hardware counters of
+/// the in-process backends.
+#[inline(never)]
+pub fn start_instrumentation() {
+    let (start, stop) = tool_requests();
+    STOP_REQUEST.store(stop, Ordering::Relaxed);
+    memory::start();
+    counter::reset();
+
+    client_request(0, start, [0; 5]);
+    wallclock::start();
+    perf::start();
+}
+
+/// Stop counting events in the benchmark
+#[inline(never)]
+pub fn stop_instrumentation() {
+    perf::stop();
+    wallclock::stop();
+    client_request(0, STOP_REQUEST.load(Ordering::Relaxed), [0; 5]);
+    memory::stop();
+}
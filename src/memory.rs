@@ -0,0 +1,179 @@
+//! Peak and total heap usage of a benchmark
+//!
+//! If the benchmark binary installs `CountingAllocator` as global allocator, the child process
+//! reports its heap usage to the parent through the file in `ALCO_MEMORY_FILE`. Otherwise the
+//! peak heap size is measured with massif, if valgrind is available.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    path::PathBuf,
+    process::Command,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::child::{child_args, run_child, sample_name};
+use crate::error::{Error, Result};
+use crate::params::ParamSamples;
+use crate::valgrind::{ValgrindConfig, massif::run_massif};
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static BASE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static TOTAL: AtomicUsize = AtomicUsize::new(0);
+static FROZEN: AtomicBool = AtomicBool::new(true);
+
+/// Global allocator counting the peak and total heap usage of a benchmark
+///
+/// Install it in the benchmark binary with
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOC: alco::CountingAllocator = alco::CountingAllocator::new();
+/// ```
+#[derive(Default)]
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    pub const fn new() -> Self {
+        CountingAllocator
+    }
+}
+
+fn record_alloc(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    let current = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+
+    if !FROZEN.load(Ordering::Relaxed) {
+        TOTAL.fetch_add(size, Ordering::Relaxed);
+        PEAK.fetch_max(current, Ordering::Relaxed);
+    }
+}
+
+fn record_dealloc(size: usize) {
+    CURRENT.fetch_sub(size, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        record_dealloc(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record_dealloc(layout.size());
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// Start counting heap usage relative to the current heap size
+pub fn start() {
+    let current = CURRENT.load(Ordering::Relaxed);
+    BASE.store(current, Ordering::Relaxed);
+    PEAK.store(current, Ordering::Relaxed);
+    TOTAL.store(0, Ordering::Relaxed);
+    FROZEN.store(false, Ordering::Relaxed);
+}
+
+/// Stop counting heap usage, later allocations are ignored
+pub fn stop() {
+    FROZEN.store(true, Ordering::Relaxed);
+}
+
+/// Heap usage of a single run
+#[derive(Clone, Debug)]
+pub struct MemoryStats {
+    /// Maximal heap size during the run
+    pub peak_bytes: u64,
+    /// Bytes allocated over the whole run, only available with `CountingAllocator`
+    pub total_bytes: Option<u64>,
+}
+
+fn memory_file(name: &str, params: &ParamSamples) -> PathBuf {
+    PathBuf::from(format!("target/alco/memory.out.{}.{}", name, sample_name(params)))
+}
+
+/// Write the heap usage to the side channel of the parent, if requested and the counting allocator
+/// is installed
+pub fn write_report() {
+    let file = match std::env::var_os("ALCO_MEMORY_FILE") {
+        Some(file) => file,
+        None => return,
+    };
+
+    if ALLOCATIONS.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+
+    let peak = PEAK.load(Ordering::Relaxed) - BASE.load(Ordering::Relaxed);
+    let total = TOTAL.load(Ordering::Relaxed);
+
+    std::fs::write(file, format!("{} {}", peak, total))
+        .expect("Unable to write memory report");
+}
+
+fn read_report(file: &PathBuf) -> Result<Option<MemoryStats>> {
+    let content = match std::fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(_) => return Ok(None),
+    };
+    let mut parts = content.split_whitespace().map(|x| x.parse::<u64>());
+
+    match (parts.next(), parts.next()) {
+        (Some(Ok(peak_bytes)), Some(Ok(total_bytes))) => Ok(Some(MemoryStats {
+            peak_bytes,
+            total_bytes: Some(total_bytes),
+        })),
+        _ => Err(Error::ParseOutput("memory report".to_string(), content)),
+    }
+}
+
+/// Measure the heap usage of benchmark `i` natively with the counting allocator, or under massif
+/// if the allocator is not installed
+///
+/// Returns `None` if the allocator is not installed and valgrind is not available.
+pub fn measure_memory(
+    config: Option<&ValgrindConfig>,
+    executable: &str,
+    i: usize,
+    params: &ParamSamples,
+    name: &str,
+) -> Result<Option<MemoryStats>> {
+    let file = memory_file(name, params);
+    std::fs::create_dir_all(file.parent().unwrap())?;
+
+    if file.exists() {
+        std::fs::remove_file(&file)?;
+    }
+
+    let mut cmd = Command::new(executable);
+    run_child(child_args(&mut cmd, i, params).env("ALCO_MEMORY_FILE", &file), "native", name, params)?;
+
+    match (read_report(&file)?, config) {
+        (Some(stats), _) => Ok(Some(stats)),
+        (None, Some(config)) => Ok(Some(MemoryStats {
+            peak_bytes: run_massif(config, executable, i, params, name)?,
+            total_bytes: None,
+        })),
+        (None, None) => Ok(None),
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use crate::valgrind::cachegrind::CachegrindStats;
+
+/// Cost measured by a backend for which the complexity is estimated
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Metric {
+    /// Number of executed instructions (`Ir`)
+    InstructionReads,
+    /// Number of memory reads (`Dr`)
+    DataReads,
+    /// Number of memory writes (`Dw`)
+    DataWrites,
+    /// Misses in the first level caches (`I1mr + D1mr + D1mw`)
+    L1Misses,
+    /// Misses in the last level cache (`ILmr + DLmr + DLmw`)
+    LLMisses,
+    /// Weighted sum of cache hits `L1 + 5 * LL + 35 * RAM`, similar to iai
+    EstimatedCycles,
+    /// Number of CPU cycles, measured with hardware counters
+    Cycles,
+    /// Median wall-clock time in nanoseconds
+    WallTime,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 8] = [
+        Metric::InstructionReads,
+        Metric::DataReads,
+        Metric::DataWrites,
+        Metric::L1Misses,
+        Metric::LLMisses,
+        Metric::EstimatedCycles,
+        Metric::Cycles,
+        Metric::WallTime,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Metric::ALL.iter().copied().find(|x| x.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::InstructionReads => "instructions",
+            Metric::DataReads => "data reads",
+            Metric::DataWrites => "data writes",
+            Metric::L1Misses => "L1 misses",
+            Metric::LLMisses => "LL misses",
+            Metric::EstimatedCycles => "estimated cycles",
+            Metric::Cycles => "cycles",
+            Metric::WallTime => "wall time [ns]",
+        }
+    }
+
+    pub fn requires_cache_sim(&self) -> bool {
+        !matches!(self, Metric::InstructionReads | Metric::Cycles | Metric::WallTime)
+    }
+}
+
+/// Costs of a single run, independent of the backend which measured them
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    /// Value and variance of each measured metric
+    values: HashMap<Metric, (u64, f64)>,
+    /// The run was stopped early and the values are lower bounds of its costs
+    censored: bool,
+}
+
+impl Stats {
+    pub fn insert(&mut self, metric: Metric, value: u64) {
+        self.values.insert(metric, (value, 0.0));
+    }
+
+    /// Insert a metric together with the variance of its estimate, e.g. for wall-clock time
+    pub fn insert_noisy(&mut self, metric: Metric, value: u64, variance: f64) {
+        self.values.insert(metric, (value, variance));
+    }
+
+    pub fn get(&self, metric: Metric) -> Option<u64> {
+        self.values.get(&metric).map(|x| x.0)
+    }
+
+    /// Iterate over all measured metrics with their value and variance
+    pub fn iter(&self) -> impl Iterator<Item = (Metric, u64, f64)> + '_ {
+        self.values.iter().map(|(metric, (value, variance))| (*metric, *value, *variance))
+    }
+
+    /// Variance of the metric, zero for deterministic backends
+    pub fn variance(&self, metric: Metric) -> f64 {
+        self.values.get(&metric).map(|x| x.1).unwrap_or(0.0)
+    }
+
+    /// Mark the values as lower bounds of a run that was stopped before it finished
+    pub fn censored(mut self) -> Self {
+        self.censored = true;
+
+        self
+    }
+
+    pub fn is_censored(&self) -> bool {
+        self.censored
+    }
+
+    /// Raise every metric to at least its value in `other`
+    pub fn max(&mut self, other: &Stats) {
+        for (metric, (value, variance)) in &other.values {
+            let entry = self.values.entry(*metric).or_insert((0, 0.0));
+            if *value > entry.0 {
+                *entry = (*value, *variance);
+            }
+        }
+    }
+
+    /// Combine the runs of a sample point with different seeds into their mean
+    ///
+    /// The variance is the one of the mean, consisting of the spread between the runs and the noise
+    /// of each run. The combination is censored if any run was stopped early.
+    pub fn combine(runs: &[Stats]) -> Self {
+        if let [stats] = runs {
+            return stats.clone();
+        }
+
+        let mut combined = Stats::default();
+        let num_runs = runs.len() as f64;
+
+        for metric in Metric::ALL {
+            let values = runs.iter()
+                .filter_map(|x| x.values.get(&metric))
+                .collect::<Vec<_>>();
+
+            if values.len() < runs.len() {
+                continue;
+            }
+
+            let mean = values.iter().map(|x| x.0 as f64).sum::<f64>() / num_runs;
+            let spread = values.iter().map(|x| (x.0 as f64 - mean).powi(2)).sum::<f64>() / (num_runs - 1.0);
+            let noise = values.iter().map(|x| x.1).sum::<f64>() / num_runs;
+
+            combined.insert_noisy(metric, mean.round() as u64, (spread + noise) / num_runs);
+        }
+
+        combined.censored = runs.iter().any(|x| x.censored);
+
+        combined
+    }
+
+    /// Collect all metrics available in the cachegrind output
+    pub fn from_cachegrind(stats: &CachegrindStats) -> Self {
+        let mut values = Stats::default();
+        values.insert(Metric::InstructionReads, stats.instruction_reads);
+
+        if let Some(data_reads) = stats.data_reads {
+            values.insert(Metric::DataReads, data_reads);
+        }
+        if let Some(data_writes) = stats.data_writes {
+            values.insert(Metric::DataWrites, data_writes);
+        }
+        if let (Some(i1), Some(d1r), Some(d1w)) = (stats.instruction_l1_misses, stats.data_l1_read_misses, stats.data_l1_write_misses) {
+            values.insert(Metric::L1Misses, i1 + d1r + d1w);
+        }
+        if let Some(ram_accesses) = stats.ram_accesses() {
+            values.insert(Metric::LLMisses, ram_accesses);
+        }
+        if let Some(summary) = stats.summarize() {
+            values.insert(Metric::EstimatedCycles, summary.l1_hits + 5 * summary.l3_hits + 35 * summary.ram_hits);
+        }
+
+        values
+    }
+}
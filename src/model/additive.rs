@@ -0,0 +1,485 @@
+//! Additive models `c_1 t_1 + ... + c_k t_k` estimated with a beam search over the terms
+//!
+//! Every term is a product of complexity classes of up to `max_interactions` parameters. The
+//! coefficients minimize the weighted squared relative error, so that small and large sample points
+//! contribute equally. Terms are added as long as they improve the Bayesian information criterion.
+//!
+//! Censored observations are lower bounds of the cost. They only enter the fit while the model
+//! predicts less than their bound, a model predicting more explains them perfectly.
+
+use std::fmt;
+
+use itertools::Itertools;
+
+use crate::params::ParamSamples;
+use super::classes::{value, Class};
+use super::Observation;
+
+/// Maximal number of terms of a model
+const MAX_TERMS: usize = 4;
+
+/// Smallest relative error, exact fits would otherwise have an infinitely good score
+const MIN_ERROR: f64 = 1e-12;
+
+/// Product of complexity classes, the constant term has no factors
+type Term = Vec<(String, Class)>;
+
+/// Estimated complexity of a cost
+#[derive(Clone, Debug, Default)]
+pub struct Model {
+    terms: Vec<(Term, f64)>,
+    /// Bayesian information criterion of the fit, smaller is better
+    score: f64,
+}
+
+impl Model {
+    /// Predicted cost at a sample point, `None` if a parameter of the model is missing
+    pub fn predict(&self, params: &ParamSamples) -> Option<f64> {
+        self.terms.iter()
+            .map(|(term, coefficient)| Some(coefficient * eval(term, &values(term, params)?)))
+            .sum()
+    }
+
+    /// Bayesian information criterion of the fit, smaller is better
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+
+        let terms = self.terms.iter()
+            .map(|(term, coefficient)| {
+                let factors = term.iter().filter_map(|(name, class)| class.format(name)).collect::<Vec<_>>();
+
+                match factors.is_empty() {
+                    true => format!("{:.3}", coefficient),
+                    false => format!("{:.3} {}", coefficient, factors.join(" ")),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", terms.join(" + "))
+    }
+}
+
+fn values(term: &Term, params: &ParamSamples) -> Option<Vec<f64>> {
+    term.iter().map(|(name, _)| value(params, name)).collect()
+}
+
+fn eval(term: &Term, values: &[f64]) -> f64 {
+    term.iter().zip(values).map(|((_, class), x)| class.ln_eval(*x)).sum::<f64>().exp()
+}
+
+/// Observation prepared for fitting, with the value of every term
+struct Row {
+    features: Vec<f64>,
+    cost: f64,
+    weight: f64,
+    censored: bool,
+}
+
+impl Row {
+    fn predict(&self, coefficients: &[f64]) -> f64 {
+        self.features.iter().zip(coefficients).map(|(x, c)| x * c).sum()
+    }
+
+    /// Residual of the fit, zero for censored rows below the prediction
+    fn residual(&self, coefficients: &[f64]) -> f64 {
+        let residual = self.cost - self.predict(coefficients);
+
+        match self.censored {
+            true => residual.max(0.0),
+            false => residual,
+        }
+    }
+}
+
+/// Maximal number of rounds adding censored rows violated by the fit
+const MAX_CENSORED_ROUNDS: usize = 10;
+
+/// All terms with up to `max_interactions` factors, including the constant one
+fn candidate_terms(names: &[String], max_interactions: usize) -> Vec<Term> {
+    let classes = Class::ALL.iter().copied().filter(|x| *x != Class::Constant).collect::<Vec<_>>();
+
+    // the cartesian product of zero factors is empty instead of the empty term
+    let constant = std::iter::once(Term::new());
+
+    let products = (1..=max_interactions.min(names.len()))
+        .flat_map(|num| names.iter().cloned().combinations(num))
+        .flat_map(|names| {
+            names.iter()
+                .map(|_| classes.iter().copied())
+                .multi_cartesian_product()
+                .map(move |classes| names.iter().cloned().zip(classes).collect::<Term>())
+        });
+
+    constant.chain(products).collect()
+}
+
+/// Fit the coefficients of `terms` and score the model
+///
+/// Returns `None` if the terms are linearly dependent or a coefficient is negative.
+fn fit_terms(terms: &[Term], observations: &[(Observation, Vec<Option<f64>>)], all_terms: &[Term]) -> Option<Model> {
+    let indices = terms.iter()
+        .map(|term| all_terms.iter().position(|x| x == term))
+        .collect::<Option<Vec<_>>>()?;
+
+    let rows = observations.iter()
+        .filter_map(|(observation, features)| {
+            Some(Row {
+                features: indices.iter().map(|x| features[*x]).collect::<Option<Vec<_>>>()?,
+                cost: observation.cost as f64,
+                weight: observation.weight as f64,
+                censored: observation.censored,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if rows.is_empty() {
+        return None;
+    }
+
+    let coefficients = solve_censored(&rows, terms.len())?;
+    let error = relative_error(&rows, &coefficients);
+
+    // Bayesian information criterion, terms have to pay for themselves
+    let num = rows.len() as f64;
+    let score = num * error.max(MIN_ERROR).ln() + terms.len() as f64 * num.ln();
+
+    Some(Model {
+        terms: terms.iter().cloned().zip(coefficients).collect(),
+        score,
+    })
+}
+
+/// Weight of a row in the least-squares problem, the squared relative residual is weighted by the
+/// weight of the observation
+fn row_weight(row: &Row) -> f64 {
+    row.weight / row.cost.max(1.0).powi(2)
+}
+
+fn relative_error(rows: &[Row], coefficients: &[f64]) -> f64 {
+    let total_weight = rows.iter().map(|x| x.weight).sum::<f64>();
+    if total_weight <= 0.0 {
+        return 0.0;
+    }
+
+    rows.iter()
+        .map(|row| row_weight(row) * row.residual(coefficients).powi(2))
+        .sum::<f64>() / total_weight
+}
+
+/// Fit the uncensored rows, then add censored rows whose bound is violated until none is left
+fn solve_censored(rows: &[Row], num_terms: usize) -> Option<Vec<f64>> {
+    let mut included = rows.iter().map(|x| !x.censored).collect::<Vec<_>>();
+    if !included.contains(&true) {
+        included.iter_mut().for_each(|x| *x = true);
+    }
+
+    let mut coefficients = Vec::new();
+    for _ in 0..MAX_CENSORED_ROUNDS {
+        let active = rows.iter().zip(&included).filter(|x| *x.1).map(|x| x.0).collect::<Vec<_>>();
+        coefficients = solve_relative(&active, num_terms)?;
+
+        let mut violated = false;
+        for (row, included) in rows.iter().zip(included.iter_mut()) {
+            if !*included && row.residual(&coefficients) > 0.0 {
+                *included = true;
+                violated = true;
+            }
+        }
+
+        if !violated {
+            break;
+        }
+    }
+
+    Some(coefficients)
+}
+
+/// Weighted linear least squares with non-negative coefficients
+fn solve_relative(rows: &[&Row], num_terms: usize) -> Option<Vec<f64>> {
+    if num_terms == 0 {
+        return Some(Vec::new());
+    }
+
+    // scale the columns to improve the condition of the normal equations
+    let scales = (0..num_terms)
+        .map(|j| rows.iter().map(|x| x.features[j].abs()).fold(0.0, f64::max))
+        .map(|x| if x > 0.0 { x } else { 1.0 })
+        .collect::<Vec<_>>();
+
+    let mut matrix = vec![vec![0.0; num_terms + 1]; num_terms];
+    for row in rows {
+        let weight = row_weight(row);
+        for i in 0..num_terms {
+            let x = row.features[i] / scales[i];
+            for j in 0..num_terms {
+                matrix[i][j] += weight * x * row.features[j] / scales[j];
+            }
+            matrix[i][num_terms] += weight * x * row.cost;
+        }
+    }
+
+    let solution = gauss(matrix)?;
+    if solution.iter().any(|x| *x < 0.0 || !x.is_finite()) {
+        return None;
+    }
+
+    Some(solution.iter().zip(&scales).map(|(x, scale)| x / scale).collect())
+}
+
+/// Solve the augmented system with gaussian elimination and partial pivoting
+fn gauss(mut matrix: Vec<Vec<f64>>) -> Option<Vec<f64>> {
+    let num = matrix.len();
+    let norm = matrix.iter().flat_map(|x| x[..num].iter()).fold(0.0, |a: f64, b| a.max(b.abs()));
+
+    for column in 0..num {
+        let pivot = (column..num).max_by(|a, b| matrix[*a][column].abs().total_cmp(&matrix[*b][column].abs()))?;
+        if matrix[pivot][column].abs() <= norm * 1e-10 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+
+        let (upper, lower) = matrix.split_at_mut(column + 1);
+        let pivot = &upper[column];
+        for row in lower {
+            let factor = row[column] / pivot[column];
+            for (x, y) in row[column..].iter_mut().zip(&pivot[column..]) {
+                *x -= factor * y;
+            }
+        }
+    }
+
+    let mut solution = vec![0.0; num];
+    for row in (0..num).rev() {
+        let rest = (row + 1..num).map(|k| matrix[row][k] * solution[k]).sum::<f64>();
+        solution[row] = (matrix[row][num] - rest) / matrix[row][row];
+    }
+
+    Some(solution)
+}
+
+/// Estimate an additive model with a beam search over the terms
+///
+/// Observations with a zero cost are skipped, their relative error is unbounded for any positive
+/// prediction. Without other observations the model is zero.
+pub fn fit(observations: Vec<Observation>, beam_size: usize, max_interactions: usize) -> Model {
+    fit_best(observations, beam_size, max_interactions, 1).pop().unwrap_or_default()
+}
+
+/// Estimate additive models like `fit` and return the `num` best ones of the beam search, best
+/// first
+pub fn fit_best(observations: Vec<Observation>, beam_size: usize, max_interactions: usize, num: usize) -> Vec<Model> {
+    let observations = observations.into_iter().filter(|x| x.cost > 0).collect::<Vec<_>>();
+
+    // parameters with a positive numeric value at every sample point, derived parameters would
+    // only duplicate the terms of the parameters they are computed from
+    let names = observations.first()
+        .map(|x| x.params.clone().samples().into_keys().collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|name| observations.iter().all(|x| value(&x.params, name).is_some() && !x.params.is_derived(name)))
+        .sorted()
+        .collect::<Vec<_>>();
+
+    let all_terms = candidate_terms(&names, max_interactions);
+    let observations = observations.into_iter()
+        .map(|observation| {
+            let features = all_terms.iter()
+                .map(|term| Some(eval(term, &values(term, &observation.params)?)))
+                .collect();
+
+            (observation, features)
+        })
+        .collect::<Vec<_>>();
+
+    let mut best = match fit_terms(&[], &observations, &all_terms) {
+        Some(model) => model,
+        None => return Vec::new(),
+    };
+    let mut models = vec![best.clone()];
+    let mut beam = vec![Vec::new()];
+
+    for _ in 0..MAX_TERMS {
+        let mut next = beam.iter()
+            .flat_map(|terms: &Vec<Term>| {
+                all_terms.iter()
+                    .filter(|term| !terms.contains(term))
+                    .map(|term| {
+                        let mut terms = terms.clone();
+                        terms.push(term.clone());
+                        terms.sort();
+                        terms
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unique()
+            .filter_map(|terms| fit_terms(&terms, &observations, &all_terms))
+            .collect::<Vec<_>>();
+
+        next.sort_by(|a, b| a.score.total_cmp(&b.score));
+        next.truncate(beam_size.max(1));
+        models.extend(next.iter().cloned());
+
+        match next.first() {
+            Some(model) if model.score < best.score => best = model.clone(),
+            _ => break,
+        }
+
+        beam = next.into_iter()
+            .map(|model| model.terms.into_iter().map(|x| x.0).collect())
+            .collect();
+    }
+
+    models.sort_by(|a, b| a.score.total_cmp(&b.score));
+    models.truncate(num);
+
+    models
+}
+
+/// Spread of the logarithmic costs the models predict for `params`
+///
+/// Zero if the models agree or at most one of them can predict the cost.
+pub fn disagreement(models: &[Model], params: &ParamSamples) -> f64 {
+    let predictions = models.iter()
+        .filter_map(|x| Some(x.predict(params)?.max(1.0).ln()))
+        .collect::<Vec<_>>();
+
+    let min = predictions.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = predictions.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+    (max - min).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::params::Sample;
+
+    fn params(n: usize) -> ParamSamples {
+        ParamSamples::new(HashMap::from([("n".to_string(), Sample::Usize(n))]))
+    }
+
+    fn observation(n: usize, cost: u64, weight: f32) -> Observation {
+        Observation { weight, ..Observation::new(params(n), cost) }
+    }
+
+    #[test]
+    fn fits_linear_cost() {
+        let observations = (1..=20).map(|n| observation(n * 10, 100 * n as u64 * 10, 1.0)).collect();
+        let model = fit(observations, 4, 3);
+
+        let prediction = model.predict(&params(1000)).unwrap();
+        assert!((prediction - 100_000.0).abs() < 1.0, "{} predicts {}", model, prediction);
+    }
+
+    #[test]
+    fn fits_constant_cost() {
+        let observations = (1..=20).map(|n| observation(n * 10, 100_000, 1.0)).collect();
+        let model = fit(observations, 4, 3);
+
+        assert_eq!(model.terms.len(), 1, "{}", model);
+        assert!(model.terms[0].0.is_empty(), "{}", model);
+        assert!((model.predict(&params(1000)).unwrap() - 100_000.0).abs() < 1.0, "{}", model);
+    }
+
+    #[test]
+    fn fits_constant_plus_linear_cost() {
+        let observations = (1..=20).map(|n| observation(n * 100, 100_000 + 10 * n as u64 * 100, 1.0)).collect();
+        let model = fit(observations, 4, 3);
+
+        let prediction = model.predict(&params(100_000)).unwrap();
+        assert!((prediction - 1_100_000.0).abs() < 10.0, "{} predicts {}", model, prediction);
+        assert_eq!(model.terms.len(), 2, "{}", model);
+    }
+
+    #[test]
+    fn skips_zero_costs() {
+        // small inputs stay below the resolution of the measurement
+        let observations = (1..=20)
+            .map(|n| observation(n * 10, if n < 3 { 0 } else { 8 * n as u64 * 10 }, 1.0))
+            .collect();
+
+        let model = fit(observations, 4, 3);
+        assert_eq!(model.to_string(), "8.000 n");
+        assert_eq!(fit(vec![observation(10, 0, 1.0)], 4, 3).to_string(), "0");
+    }
+
+    #[test]
+    fn ignores_derived_parameters() {
+        let observations = (1..=20)
+            .map(|n| {
+                let params = ParamSamples::new(HashMap::from([
+                    ("n".to_string(), Sample::Usize(n * 10)),
+                    ("m".to_string(), Sample::Usize(n * n * 100)),
+                ]));
+
+                Observation::new(params.with_derived(vec!["m".to_string()]), 5 * (n * n) as u64 * 100)
+            })
+            .collect();
+
+        let model = fit(observations, 4, 3);
+        assert_eq!(model.to_string(), "5.000 n^2");
+    }
+
+    #[test]
+    fn prefers_points_where_models_disagree() {
+        let observations = (1..=8).map(|n| observation(n, 100 * n as u64, 1.0)).collect();
+        let models = fit_best(observations, 4, 3, 4);
+        assert!(models.len() > 1);
+
+        // the models fit the measured range similarly but diverge when extrapolating
+        let mut pool = vec![params(4), params(100_000), params(2)];
+        pool.sort_by(|a, b| disagreement(&models, b).total_cmp(&disagreement(&models, a)));
+
+        assert_eq!(pool[0].get_usize("n"), Some(100_000));
+        assert!(disagreement(&models, &params(4)) < disagreement(&models, &params(100_000)));
+    }
+
+    #[test]
+    fn censored_points_bound_the_fit() {
+        let linear = (1..=10).map(|n| observation(n, 100 * n as u64, 1.0)).collect::<Vec<_>>();
+
+        // stopped runs at large sizes cost at least quadratically more
+        let mut censored = linear.clone();
+        censored.extend((60..=64).map(|n| Observation {
+            censored: true,
+            ..observation(n, 100 * (n * n) as u64, 1.0)
+        }));
+
+        let without = fit(linear, 4, 3);
+        let with = fit(censored, 4, 3);
+
+        // the lower bounds pull the fit up, at the cost of the small sample points
+        let (without, with) = (without.predict(&params(64)).unwrap(), with.predict(&params(64)).unwrap());
+        assert!((without - 6400.0).abs() < 1.0, "{}", without);
+        assert!(with > 10.0 * without, "{} vs. {}", with, without);
+    }
+
+    #[test]
+    fn down_weights_noisy_points() {
+        let observations = |weight| {
+            (1..=20)
+                .map(|n| match n {
+                    10 => observation(n, 50_000, weight),
+                    _ => observation(n, 100 * n as u64, 1.0),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let noisy = fit(observations(1.0), 4, 3);
+        let weighted = fit(observations(1e-6), 4, 3);
+
+        let error = |model: &Model| (model.predict(&params(20)).unwrap() - 2000.0).abs();
+        assert!(error(&weighted) < 1.0, "{}", weighted);
+        assert!(error(&weighted) < error(&noisy), "{} vs. {}", weighted, noisy);
+    }
+}
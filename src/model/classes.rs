@@ -0,0 +1,62 @@
+//! Complexity classes of a single parameter, the factors of the terms of additive models
+
+use crate::params::{ParamSamples, Sample};
+
+/// Growth of the cost in a single parameter
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Class {
+    Constant,
+    Logarithmic,
+    Linear,
+    Linearithmic,
+    Quadratic,
+    Cubic,
+}
+
+impl Class {
+    pub const ALL: [Class; 6] = [
+        Class::Constant,
+        Class::Logarithmic,
+        Class::Linear,
+        Class::Linearithmic,
+        Class::Quadratic,
+        Class::Cubic,
+    ];
+
+    /// Logarithm of the growth function at `x`, shifted to stay positive for small values
+    pub(super) fn ln_eval(&self, x: f64) -> f64 {
+        let log = (x + 2.0).ln();
+
+        match self {
+            Class::Constant => 0.0,
+            Class::Logarithmic => log.ln(),
+            Class::Linear => x.ln(),
+            Class::Linearithmic => x.ln() + log.ln(),
+            Class::Quadratic => 2.0 * x.ln(),
+            Class::Cubic => 3.0 * x.ln(),
+        }
+    }
+
+    /// Factor of parameter `name` in a formula, `None` for the constant class
+    pub(super) fn format(&self, name: &str) -> Option<String> {
+        match self {
+            Class::Constant => None,
+            Class::Logarithmic => Some(format!("log({})", name)),
+            Class::Linear => Some(name.to_string()),
+            Class::Linearithmic => Some(format!("{} log({})", name, name)),
+            Class::Quadratic => Some(format!("{}^2", name)),
+            Class::Cubic => Some(format!("{}^3", name)),
+        }
+    }
+}
+
+/// Numeric value of a parameter, if positive
+pub(super) fn value(params: &ParamSamples, name: &str) -> Option<f64> {
+    let value = match params.get(name)? {
+        Sample::Usize(x) => *x as f64,
+        Sample::Float(x) => *x as f64,
+        Sample::Str(_) => return None,
+    };
+
+    Some(value).filter(|x| *x > 0.0)
+}
@@ -1,9 +1,13 @@
+mod additive;
 mod chebyshev;
+mod classes;
 
 use ndarray::{Array1, Array2};
 use ndarray_linalg::least_squares::LeastSquaresSvd;
 use crate::params::ParamSamples;
 
+pub use additive::{disagreement, Model};
+
 /// https://stackoverflow.com/questions/382186/fitting-polynomials-to-data
 
 fn poly_fnc(val: f32, n: usize) -> Vec<f32> {
@@ -61,9 +65,43 @@ pub fn estimate_stepsize(results: &[(usize, u64)], min_change: u64) -> usize {
     1
 }
 
+/// Measured cost of a benchmark at a single sample point
+#[derive(Clone)]
+pub struct Observation {
+    pub params: ParamSamples,
+    pub cost: u64,
+    /// Weight of the observation, e.g. its inverse variance
+    pub weight: f32,
+    /// The run was stopped early and the cost is only a lower bound
+    pub censored: bool,
+}
+
+impl Observation {
+    pub fn new(params: ParamSamples, cost: u64) -> Self {
+        Observation {
+            params,
+            cost,
+            weight: 1.0,
+            censored: false,
+        }
+    }
+}
+
 pub fn fit_greedy_additive(results: Vec<(ParamSamples, u64)>, beam_size: usize, max_interactions: usize) -> Model {
-    Model
+    let results = results.into_iter().map(|(params, cost)| Observation::new(params, cost)).collect();
+
+    fit_weighted_additive(results, beam_size, max_interactions)
 }
 
-#[derive(Debug)]
-pub struct Model;
+/// Same as `fit_greedy_additive`, but each observation is weighted, e.g. by its inverse variance
+///
+/// Censored observations only bound the model from below, a model predicting a larger cost
+/// explains them perfectly.
+pub fn fit_weighted_additive(results: Vec<Observation>, beam_size: usize, max_interactions: usize) -> Model {
+    additive::fit(results, beam_size, max_interactions)
+}
+
+/// The `num` best models of the beam search of `fit_weighted_additive`, best first
+pub fn fit_best_additive(results: Vec<Observation>, beam_size: usize, max_interactions: usize, num: usize) -> Vec<Model> {
+    additive::fit_best(results, beam_size, max_interactions, num)
+}
@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use crate::backend::Backend;
+use crate::pool;
+use crate::valgrind::cache::CacheConfig;
+
+/// Command line options of the benchmark runner
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Backend used to explore the parameter space
+    pub backend: Backend,
+    /// Break down the cost of each benchmark by function with callgrind
+    pub callgrind: bool,
+    /// Number of functions with highest cost for which a model is fitted
+    pub num_functions: usize,
+    /// Measure heap allocations of each benchmark with DHAT
+    pub dhat: bool,
+    /// Measure the peak heap size of each benchmark
+    pub memory: bool,
+    /// Cache hierarchy simulated for benchmarks without their own configuration
+    pub cache: CacheConfig,
+    /// Kill a single run of a benchmark after this time
+    pub timeout: Duration,
+    /// Total number of instructions executed by all runs of a benchmark
+    pub budget: Option<u64>,
+    /// Number of sample points measured in parallel
+    pub jobs: usize,
+    /// Reuse and store cachegrind results in `target/alco/cache`
+    pub cache_results: bool,
+    /// Remove all cached results before running the benchmarks
+    pub clear_cache: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            backend: Backend::Cachegrind,
+            callgrind: false,
+            num_functions: 10,
+            dhat: false,
+            memory: false,
+            cache: CacheConfig::default(),
+            timeout: Duration::from_secs(60),
+            budget: Some(100_000_000_000),
+            jobs: pool::default_jobs(),
+            cache_results: true,
+            clear_cache: false,
+        }
+    }
+}
+
+impl Options {
+    pub fn from_args<I: Iterator<Item = String>>(args: I) -> Self {
+        let mut options = Options::default();
+        let mut args = args.peekable();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--backend" => {
+                    let name = args.next().unwrap_or_default();
+                    match Backend::from_str(&name) {
+                        Some(backend) => options.backend = backend,
+                        None => println!("Unknown backend `{}`, expected one of cachegrind, wallclock or perf.", name),
+                    }
+                },
+                "--callgrind" => options.callgrind = true,
+                "--dhat" => options.dhat = true,
+                "--memory" => options.memory = true,
+                "--no-cache" => options.cache_results = false,
+                "--clear-cache" => options.clear_cache = true,
+                "--cache" => {
+                    let preset = args.next().unwrap_or_default();
+                    match CacheConfig::from_preset(&preset) {
+                        Some(cache) => options.cache = cache,
+                        None => println!("Unknown cache preset `{}`, expected one of embedded, desktop or server.", preset),
+                    }
+                },
+                "--timeout" => {
+                    match args.next().and_then(|x| x.parse::<f64>().ok()) {
+                        Some(secs) if secs > 0.0 => options.timeout = Duration::from_secs_f64(secs),
+                        _ => println!("Expected a positive number of seconds after `--timeout`."),
+                    }
+                },
+                "--budget" => {
+                    match args.next().as_deref() {
+                        Some("none") => options.budget = None,
+                        Some(num) => match num.parse::<u64>() {
+                            Ok(num) => options.budget = Some(num),
+                            Err(_) => println!("Expected a number of instructions or `none` after `--budget`."),
+                        },
+                        None => println!("Expected a number of instructions or `none` after `--budget`."),
+                    }
+                },
+                "--jobs" => {
+                    match args.next().and_then(|x| x.parse::<usize>().ok()) {
+                        Some(jobs) if jobs > 0 => options.jobs = jobs,
+                        _ => println!("Expected a positive number of workers after `--jobs`."),
+                    }
+                },
+                "--functions" => {
+                    if let Some(num) = args.next_if(|x| x.parse::<usize>().is_ok()) {
+                        options.num_functions = num.parse().unwrap();
+                    }
+                },
+                // ignore arguments passed by cargo, e.g. `--bench`
+                _ => {}
+            }
+        }
+
+        options
+    }
+}
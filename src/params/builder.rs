@@ -3,19 +3,78 @@ use std::any::Any;
 use std::collections::HashMap;
 
 use crate::error::{Result, Error};
+use crate::metric::Metric;
+use crate::sampling::Design;
+use crate::valgrind::cache::{CacheConfig, LL_SIZE_PARAM};
 use super::samples::{Sample, Samples};
 
+/// Spacing of the values of a parameter, a hint for sampling and the fitter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scale {
+    Linear,
+    Logarithmic,
+}
+
+/// A parameter space, mapping steps `0, 1, 2, ...` to the values of a parameter
+///
+/// Step zero is the lower bound of the parameter and used for the calibration run. The runner
+/// increases the step of each parameter until the cost changes significantly, so the values of an
+/// ordered parameter should grow with the step. `for_step` has to return `None` for every step
+/// past the end of the space.
+///
+/// A parameter with `num_items` is categorical instead, all of its items are sampled in every
+/// combination.
+///
+/// ```ignore
+/// struct PowersOfTwo(u32);
+///
+/// impl alco::ParamType for PowersOfTwo {
+///     fn num_steps(&self) -> Option<usize> {
+///         Some(self.0 as usize)
+///     }
+///
+///     fn scale(&self) -> alco::Scale {
+///         alco::Scale::Logarithmic
+///     }
+///
+///     fn for_step(&self, step: usize) -> Option<alco::Sample> {
+///         (step < self.0 as usize).then(|| alco::Sample::Usize(1 << step))
+///     }
+/// }
+///
+/// params.add_param("n", PowersOfTwo(20))?;
+/// ```
 pub trait ParamType {
+    /// Number of items of a categorical parameter, `None` for ordered parameters
     fn num_items(&self) -> Option<usize> {
         None
     }
 
+    /// Number of valid steps, if known
+    fn num_steps(&self) -> Option<usize> {
+        None
+    }
+
+    /// Whether the values grow with the step, a hint for the fitter
+    fn is_ordered(&self) -> bool {
+        self.num_items().is_none()
+    }
+
+    fn scale(&self) -> Scale {
+        Scale::Linear
+    }
+
+    /// Value of the parameter at `step`, or `None` if the step is out of range
     fn for_step(&self, step: usize) -> Option<Sample>;
 }
 
 pub struct UsizeRange(Range<usize>, usize);
 
 impl ParamType for UsizeRange{
+    fn num_steps(&self) -> Option<usize> {
+        Some(self.0.len().div_ceil(self.1))
+    }
+
     fn for_step(&self, step: usize) -> Option<Sample> {
         let tmp = self.0.start + step * self.1;
 
@@ -28,9 +87,65 @@ impl ParamType for UsizeRange{
 }
 
 
+/// Increasing integers, evenly spaced on a logarithmic scale
+pub struct LogRange(Vec<usize>);
+
+impl LogRange {
+    /// `points` values from `range.start` to `range.end - 1`, fewer if rounding produces duplicates
+    fn evenly(range: Range<usize>, points: usize) -> Option<Self> {
+        if range.start == 0 || range.end <= range.start + 1 || points < 2 {
+            return None;
+        }
+
+        let (start, last) = (range.start as f64, (range.end - 1) as f64);
+        let values = (0..points)
+            .map(|i| (start * (last / start).powf(i as f64 / (points - 1) as f64)).round() as usize);
+
+        Some(LogRange::dedup(values))
+    }
+
+    /// The values `start * factor^i` below `end`
+    fn geometric(start: usize, factor: f64, end: usize) -> Option<Self> {
+        if start == 0 || end <= start || factor <= 1.0 {
+            return None;
+        }
+
+        let values = (0..)
+            .map(|i| (start as f64 * factor.powi(i)).round() as usize)
+            .take_while(|x| *x < end);
+
+        Some(LogRange::dedup(values))
+    }
+
+    fn dedup<I: Iterator<Item = usize>>(values: I) -> Self {
+        let mut values = values.collect::<Vec<_>>();
+        values.dedup();
+
+        LogRange(values)
+    }
+}
+
+impl ParamType for LogRange {
+    fn num_steps(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+
+    fn scale(&self) -> Scale {
+        Scale::Logarithmic
+    }
+
+    fn for_step(&self, step: usize) -> Option<Sample> {
+        self.0.get(step).map(|x| Sample::Usize(*x))
+    }
+}
+
 pub struct FloatRange(Range<f32>, f32);
 
 impl ParamType for FloatRange {
+    fn num_steps(&self) -> Option<usize> {
+        Some(((self.0.end - self.0.start) / self.1).ceil().max(0.0) as usize)
+    }
+
     fn for_step(&self, step: usize) -> Option<Sample> {
         let tmp = self.0.start + (step as f32) * self.1;
 
@@ -49,6 +164,10 @@ impl ParamType for Items {
         Some(self.0.len())
     }
 
+    fn num_steps(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+
     fn for_step(&self, step: usize) -> Option<Sample> {
         if step >= self.0.len() {
             return None
@@ -58,71 +177,290 @@ impl ParamType for Items {
     }
 }
 
+/// Name of the parameter selecting the input distribution
+pub const DISTRIBUTION_PARAM: &str = "distribution";
+
+/// Maximal number of steps by which other parameters are raised to satisfy the constraints
+const MAX_RAISE_STEPS: usize = 100_000;
+
+/// Predicate a sample point has to satisfy, see `ParamBuilder::add_constraint`
+type Constraint<'a> = Box<dyn Fn(&Samples) -> bool + 'a>;
+
+/// Computes a parameter from the others, see `ParamBuilder::add_derived`
+type Derive<'a> = Box<dyn Fn(&Samples) -> Sample + 'a>;
+
 pub struct ParamBuilder<'a> {
     map: HashMap<&'a str, Box<dyn ParamType>>,
+    constraints: Vec<Constraint<'a>>,
+    derived: Vec<(&'a str, Derive<'a>)>,
+    manual_instrumentation: bool,
+    metrics: Vec<Metric>,
+    cache: Option<CacheConfig>,
+    repetitions: usize,
+    design: Option<Design>,
 }
 
 impl<'a> ParamBuilder<'a> {
     pub fn new() -> Self {
         Self {
-            map: HashMap::new()
+            map: HashMap::new(),
+            constraints: Vec::new(),
+            derived: Vec::new(),
+            manual_instrumentation: false,
+            metrics: Vec::new(),
+            cache: None,
+            repetitions: 1,
+            design: None,
         }
     }
 
-    pub fn add_usize_range(&mut self, name: &'a str, range: Range<usize>) -> Result<()> {
+    /// Only count the region between `alco::start_instrumentation` and
+    /// `alco::stop_instrumentation` instead of subtracting a calibration run
+    pub fn manual_instrumentation(&mut self) {
+        self.manual_instrumentation = true;
+    }
+
+    pub fn has_manual_instrumentation(&self) -> bool {
+        self.manual_instrumentation
+    }
+
+    /// Run every sample point with `repetitions` different seeds of `ParamSamples::rng` and
+    /// estimate the complexity of the mean cost
+    pub fn repetitions(&mut self, repetitions: usize) {
+        self.repetitions = repetitions.max(1);
+    }
+
+    pub fn num_repetitions(&self) -> usize {
+        self.repetitions
+    }
+
+    /// Estimate the complexity of `metric` as well. The first metric added guides the sampling
+    pub fn add_metric(&mut self, metric: Metric) {
+        if !self.metrics.contains(&metric) {
+            self.metrics.push(metric);
+        }
+    }
+
+    /// Metrics for which the complexity is estimated, if empty the default metric of the backend
+    /// is used
+    pub fn metrics(&self) -> &[Metric] {
+        &self.metrics
+    }
+
+    /// Add a parameter with a custom parameter space
+    pub fn add_param<P: ParamType + 'static>(&mut self, name: &'a str, param: P) -> Result<()> {
         if self.map.contains_key(name) {
             return Err(Error::ArgumentAlreadyExists(name.to_string()));
         }
 
-        self.map.insert(name, Box::new(UsizeRange(range, 1)));
+        self.map.insert(name, Box::new(param));
 
         Ok(())
     }
 
+    pub fn add_usize_range(&mut self, name: &'a str, range: Range<usize>) -> Result<()> {
+        self.add_param(name, UsizeRange(range, 1))
+    }
+
+    /// Add `points` integers in `range` which are evenly spaced on a logarithmic scale, e.g.
+    /// `add_log_range("n", 1..1_000_000, 20)` covers six decades with 20 points
+    pub fn add_log_range(&mut self, name: &'a str, range: Range<usize>, points: usize) -> Result<()> {
+        let param = LogRange::evenly(range, points)
+            .ok_or_else(|| Error::InvalidRange(name.to_string()))?;
+        self.add_param(name, param)
+    }
+
+    /// Add the integers `start * factor^i` below `end`, e.g. powers of two with a factor of two
+    pub fn add_geometric_range(&mut self, name: &'a str, start: usize, factor: f64, end: usize) -> Result<()> {
+        let param = LogRange::geometric(start, factor, end)
+            .ok_or_else(|| Error::InvalidRange(name.to_string()))?;
+        self.add_param(name, param)
+    }
+
     pub fn add_float_range(&mut self, name: &'a str, range: Range<f32>, min_val: f32) -> Result<()> {
-        if self.map.contains_key(name) {
-            return Err(Error::ArgumentAlreadyExists(name.to_string()));
+        self.add_param(name, FloatRange(range, min_val))
+    }
+
+    /// Simulate `cache` instead of the default cache hierarchy for this benchmark
+    pub fn set_cache(&mut self, cache: CacheConfig) {
+        self.cache = Some(cache);
+    }
+
+    pub fn cache(&self) -> Option<&CacheConfig> {
+        self.cache.as_ref()
+    }
+
+    /// Choose the combinations of sample points after seeding with `design`
+    pub fn set_design(&mut self, design: Design) {
+        self.design = Some(design);
+    }
+
+    pub fn design(&self) -> Option<Design> {
+        self.design
+    }
+
+    /// Sweep over the size of the last level cache as an additional parameter `ll_size`
+    ///
+    /// The sizes are sampled like a logarithmic range. They have to be powers of two, so that the
+    /// number of sets stays a power of two for the associativity and line size of the presets, as
+    /// cachegrind requires.
+    pub fn add_ll_size_sweep<S: AsRef<[usize]>>(&mut self, sizes: S) -> Result<()> {
+        let mut sizes = sizes.as_ref().to_vec();
+        sizes.sort_unstable();
+        sizes.dedup();
+
+        if sizes.is_empty() {
+            return Err(Error::InvalidRange(LL_SIZE_PARAM.to_string()));
+        }
+        if let Some(size) = sizes.iter().find(|x| !x.is_power_of_two()) {
+            return Err(Error::InvalidCacheSize(*size));
         }
 
-        self.map.insert(name, Box::new(FloatRange(range, min_val)));
+        self.add_param(LL_SIZE_PARAM, LogRange(sizes))
+    }
 
-        Ok(())
+    /// Measure every sample point with each of the input `distributions`, e.g. `random`, `sorted`
+    /// or a user-defined one, and report the best, average and worst case
+    ///
+    /// The benchmark reads the distribution with `ParamSamples::distribution`.
+    pub fn add_distributions<S: AsRef<str>>(&mut self, distributions: &[S]) -> Result<()> {
+        let distributions = distributions.iter()
+            .map(|x| x.as_ref().to_string())
+            .collect::<Vec<_>>();
+
+        self.add_items(DISTRIBUTION_PARAM, distributions)
+    }
+
+    /// Input distributions of the benchmark, if any
+    pub fn distributions(&self) -> Option<Vec<String>> {
+        let param = self.map.get(DISTRIBUTION_PARAM)?;
+
+        (0..param.num_items()?)
+            .map(|step| match param.for_step(step)? {
+                Sample::Str(x) => Some(x),
+                _ => None,
+            })
+            .collect()
     }
 
     pub fn add_items<T: Any, S: AsRef<[T]>>(&mut self, name: &'a str, items: S) -> Result<()> {
         let items = items.as_ref();
 
-        if self.map.contains_key(name) {
-            return Err(Error::ArgumentAlreadyExists(name.to_string()));
-        }
-
         let items = items.iter().map(|x| {
             let x = x as &dyn Any;
             if let Some(x) = x.downcast_ref::<usize>() {
-                Sample::Usize(*x)
+                Ok(Sample::Usize(*x))
             } else if let Some(x) = x.downcast_ref::<f32>() {
-                Sample::Float(*x)
+                Ok(Sample::Float(*x))
             } else if let Some(x) = x.downcast_ref::<f64>() {
-                Sample::Float(*x as f32)
+                Ok(Sample::Float(*x as f32))
             } else if let Some(x) = x.downcast_ref::<String>() {
-                Sample::Str(x.to_string())
+                Ok(Sample::Str(x.to_string()))
             } else {
-                panic!("Not support type");
+                Err(Error::InvalidType(std::any::type_name::<T>().to_string()))
             }
-        }).collect();
+        }).collect::<Result<Vec<_>>>()?;
+
+        self.add_param(name, Items(items))
+    }
 
-        self.map.insert(name, Box::new(Items(items)));
+    /// Only sample combinations of parameters for which `constraint` holds, e.g.
+    /// `add_constraint(|x| x.get_usize("k") <= x.get_usize("n"))`
+    ///
+    /// The lower bound of all parameters has to satisfy every constraint.
+    pub fn add_constraint<F: Fn(&Samples) -> bool + 'a>(&mut self, constraint: F) {
+        self.constraints.push(Box::new(constraint));
+    }
+
+    /// Add a parameter computed from the other parameters, e.g.
+    /// `add_derived("m", |x| Sample::Usize(x.get_usize("n").unwrap().pow(2)))`
+    ///
+    /// Derived parameters are not sampled themselves and the models are expressed through the
+    /// parameters they are computed from. They may depend on derived parameters added before.
+    pub fn add_derived<F: Fn(&Samples) -> Sample + 'a>(&mut self, name: &'a str, derive: F) -> Result<()> {
+        if self.map.contains_key(name) || self.derived.iter().any(|x| x.0 == name) {
+            return Err(Error::ArgumentAlreadyExists(name.to_string()));
+        }
+
+        self.derived.push((name, Box::new(derive)));
 
         Ok(())
     }
 
+    /// Whether the sample point satisfies all constraints
+    pub fn is_valid(&self, samples: &Samples) -> bool {
+        self.constraints.iter().all(|constraint| constraint(samples))
+    }
+
+    /// Compute the derived parameters of a sample point
+    pub fn complete(&self, samples: Samples) -> Samples {
+        if self.derived.is_empty() {
+            return samples;
+        }
+
+        let (setup_run, seed) = (samples.is_setup(), samples.seed());
+        let mut samples = samples.samples();
+
+        for (name, derive) in &self.derived {
+            let value = derive(&Samples::new(samples.clone()));
+            samples.insert(name.to_string(), value);
+        }
+
+        let names = self.derived.iter().map(|x| x.0.to_string()).collect();
+        Samples::new(samples).setup_run(setup_run).with_seed(seed).with_derived(names)
+    }
+
     pub fn from_indices(&self, indices: Vec<(&str, usize)>) -> Option<Samples> {
         let res = indices.into_iter().map(|(name, val)| {
             let t = self.map.get(name).unwrap();
             t.for_step(val).map(|x| (name.to_string(), x))
         }).collect::<Option<HashMap<_, _>>>();
 
-        res.map(Samples::new)
+        res.map(|x| self.complete(Samples::new(x)))
+    }
+
+    /// Sample point with parameter `name` at `step` and all other parameters at their lower bound
+    ///
+    /// If this violates a constraint, the other ordered parameters are raised one after another
+    /// until the constraints are satisfied. Returns `None` if this is not possible.
+    pub fn seeding_sample(&self, name: &str, step: usize) -> Option<Samples> {
+        let mut indices = self.map.keys()
+            .map(|key| (*key, if *key == name { step } else { 0 }))
+            .collect::<Vec<_>>();
+
+        let samples = self.from_indices(indices.clone())?;
+        if self.is_valid(&samples) {
+            return Some(samples);
+        }
+
+        // raise other parameters in a fixed order
+        let mut others = self.map.iter()
+            .filter(|(key, param)| **key != name && param.is_ordered())
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        others.sort();
+
+        for other in others {
+            let index = indices.iter().position(|x| x.0 == other).unwrap();
+            let num_steps = self.map[other].num_steps().unwrap_or(MAX_RAISE_STEPS).min(MAX_RAISE_STEPS);
+
+            for other_step in 1..num_steps {
+                indices[index].1 = other_step;
+
+                // the end of this parameter's range, continue with the next one
+                let samples = match self.from_indices(indices.clone()) {
+                    Some(samples) => samples,
+                    None => break,
+                };
+                if self.is_valid(&samples) {
+                    return Some(samples);
+                }
+            }
+
+            indices[index].1 = 0;
+        }
+
+        None
     }
 
     pub fn lower_bound(&self) -> Samples {
@@ -135,18 +473,18 @@ impl<'a> ParamBuilder<'a> {
         &self.map
     }
 
-    pub fn update_step(&self, prev: Samples, name: &str, step: usize) -> Samples {
+    pub fn update_step(&self, prev: Samples, name: &str, step: usize) -> Result<Samples> {
         let mut samples = prev.samples();
 
         // create new value with given step
-        let new_val = {
-            let t = self.map.get(name).unwrap();
-            t.for_step(step).unwrap()
-        };
+        let new_val = self.map.get(name)
+            .ok_or_else(|| Error::UnknownArgument(name.to_string()))?
+            .for_step(step)
+            .ok_or_else(|| Error::StepOutOfRange(name.to_string(), step))?;
 
         // insert into map and return a new sample set
         samples.insert(name.to_string(), new_val);
 
-        Samples::new(samples)
+        Ok(self.complete(Samples::new(samples)))
     }
 }
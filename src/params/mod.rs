@@ -1,5 +1,5 @@
 mod builder;
 mod samples;
 
-pub use builder::ParamBuilder;
-pub use samples::Samples as ParamSamples;
+pub use builder::{ParamBuilder, ParamType, Scale, DISTRIBUTION_PARAM};
+pub use samples::{Sample, Samples as ParamSamples};
@@ -1,7 +1,12 @@
 use std::collections::HashMap;
+use std::fmt;
+
+use rand::{rngs::StdRng, SeedableRng};
+
 use crate::error::{Result, Error};
 
-#[derive(Clone)]
+/// Value of a parameter at a sample point
+#[derive(Clone, Debug)]
 pub enum Sample {
     Float(f32),
     Usize(usize),
@@ -28,7 +33,7 @@ impl Sample {
         }
     }
 
-    pub fn to_string(&self) -> String {
+    pub fn to_arg(&self) -> String {
         match self {
             Sample::Float(f) => format!("float§{}", f),
             Sample::Usize(u) => format!("usize§{}", u),
@@ -37,17 +42,31 @@ impl Sample {
     }
 }
 
-#[derive(Clone)]
+impl fmt::Display for Sample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Sample::Float(x) => write!(f, "{}", x),
+            Sample::Usize(x) => write!(f, "{}", x),
+            Sample::Str(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Samples {
     setup_run: bool,
-    args: HashMap<String, Sample>
+    seed: u64,
+    args: HashMap<String, Sample>,
+    derived: Vec<String>,
 }
 
 impl Samples {
     pub fn new(args: HashMap<String, Sample>) -> Self {
         Samples { 
             setup_run: false,
-            args
+            seed: 0,
+            args,
+            derived: Vec::new(),
         }
     }
 
@@ -60,17 +79,23 @@ impl Samples {
 
         Ok(Samples {
             setup_run,
+            seed: 0,
             args: parsed_params,
+            derived: Vec::new(),
         })
     }
 
-    pub fn to_string(&self) -> Vec<String> {
+    pub fn to_args(&self) -> Vec<String> {
         self.args.iter().map(|(name, val)| {
-                format!("{}§{}", name, val.to_string())
+                format!("{}§{}", name, val.to_arg())
             })
             .collect::<Vec<_>>()
     }
 
+    pub fn get(&self, name: &str) -> Option<&Sample> {
+        self.args.get(name)
+    }
+
     pub fn get_usize(&self, name: &str) -> Option<usize> {
         match self.args.get(name) {
             Some(Sample::Usize(x)) => Some(*x),
@@ -78,6 +103,26 @@ impl Samples {
         }
     }
 
+    /// Input distribution of the sample point, see `ParamBuilder::add_distributions`
+    pub fn distribution(&self) -> Option<&str> {
+        match self.args.get(super::DISTRIBUTION_PARAM) {
+            Some(Sample::Str(x)) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Whether the parameter is computed from other parameters instead of being sampled, see
+    /// `ParamBuilder::add_derived`
+    pub fn is_derived(&self, name: &str) -> bool {
+        self.derived.iter().any(|x| x == name)
+    }
+
+    pub(crate) fn with_derived(mut self, derived: Vec<String>) -> Self {
+        self.derived = derived;
+
+        self
+    }
+
     pub fn samples(self) -> HashMap<String, Sample> {
         self.args
     }
@@ -91,4 +136,36 @@ impl Samples {
 
         self
     }
+
+    /// Seed of the repetition, see `ParamBuilder::repetitions`
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+
+        self
+    }
+
+    /// Random number generator seeded for the repetition of this sample point
+    ///
+    /// Every run of the same repetition draws the same numbers, so that instruction counts are
+    /// reproducible.
+    pub fn rng(&self) -> StdRng {
+        StdRng::seed_from_u64(self.seed)
+    }
+}
+
+impl fmt::Display for Samples {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut args = self.args.iter().collect::<Vec<_>>();
+        args.sort_by_key(|x| x.0);
+
+        let args = args.into_iter()
+            .map(|(name, val)| format!("{}={}", name, val))
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", args.join(", "))
+    }
 }
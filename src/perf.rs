@@ -0,0 +1,249 @@
+//! Hardware counters read with `perf_event_open` on linux
+//!
+//! The counters are read in-process around the benchmark body, or around the region between
+//! `start_instrumentation` and `stop_instrumentation`. In containers and virtual machines the
+//! counters are often unavailable, in which case `PerfCounters::new` returns `None`.
+
+use std::cell::{Cell, RefCell};
+
+use crate::metric::{Metric, Stats};
+use crate::params::ParamSamples;
+use crate::wallclock::{median_mad, median_variance};
+
+/// Number of repetitions of a sample point, hardware counters are much less noisy than time
+const REPETITIONS: usize = 5;
+
+thread_local! {
+    static COUNTERS: RefCell<Option<PerfCounters>> = const { RefCell::new(None) };
+    static REGION_USED: Cell<bool> = const { Cell::new(false) };
+}
+
+#[cfg(all(target_os = "linux", feature = "perf"))]
+mod sys {
+    /// Prefix of `struct perf_event_attr` up to `PERF_ATTR_SIZE_VER5`
+    #[repr(C)]
+    #[derive(Default)]
+    struct PerfEventAttr {
+        type_: u32,
+        size: u32,
+        config: u64,
+        sample_period: u64,
+        sample_type: u64,
+        read_format: u64,
+        flags: u64,
+        wakeup_events: u32,
+        bp_type: u32,
+        config1: u64,
+        config2: u64,
+        branch_sample_type: u64,
+        sample_regs_user: u64,
+        sample_stack_user: u32,
+        clockid: i32,
+        sample_regs_intr: u64,
+        aux_watermark: u32,
+        sample_max_stack: u16,
+        reserved: u16,
+    }
+
+    const PERF_TYPE_HARDWARE: u32 = 0;
+
+    pub const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+    pub const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+    pub const PERF_COUNT_HW_CACHE_MISSES: u64 = 3;
+
+    const FLAG_DISABLED: u64 = 1 << 0;
+    const FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+    const FLAG_EXCLUDE_HV: u64 = 1 << 6;
+
+    const PERF_EVENT_IOC_ENABLE: libc::c_ulong = 0x2400;
+    const PERF_EVENT_IOC_DISABLE: libc::c_ulong = 0x2401;
+    const PERF_EVENT_IOC_RESET: libc::c_ulong = 0x2403;
+
+    pub struct Counter(libc::c_int);
+
+    impl Counter {
+        /// Open a disabled counter of the calling thread, counting user space only
+        pub fn open(config: u64) -> Option<Self> {
+            let attr = PerfEventAttr {
+                type_: PERF_TYPE_HARDWARE,
+                size: std::mem::size_of::<PerfEventAttr>() as u32,
+                config,
+                flags: FLAG_DISABLED | FLAG_EXCLUDE_KERNEL | FLAG_EXCLUDE_HV,
+                ..Default::default()
+            };
+
+            let fd = unsafe {
+                libc::syscall(libc::SYS_perf_event_open, &attr as *const PerfEventAttr, 0, -1, -1, 0)
+            };
+
+            if fd < 0 {
+                None
+            } else {
+                Some(Counter(fd as libc::c_int))
+            }
+        }
+
+        pub fn reset(&self) {
+            unsafe { libc::ioctl(self.0, PERF_EVENT_IOC_RESET, 0) };
+        }
+
+        pub fn enable(&self) {
+            unsafe { libc::ioctl(self.0, PERF_EVENT_IOC_ENABLE, 0) };
+        }
+
+        pub fn disable(&self) {
+            unsafe { libc::ioctl(self.0, PERF_EVENT_IOC_DISABLE, 0) };
+        }
+
+        pub fn read(&self) -> u64 {
+            let mut value = 0u64;
+            let size = std::mem::size_of::<u64>();
+            let read = unsafe { libc::read(self.0, &mut value as *mut u64 as *mut libc::c_void, size) };
+
+            if read as usize == size {
+                value
+            } else {
+                0
+            }
+        }
+    }
+
+    impl Drop for Counter {
+        fn drop(&mut self) {
+            unsafe { libc::close(self.0) };
+        }
+    }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "perf")))]
+mod sys {
+    pub const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+    pub const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+    pub const PERF_COUNT_HW_CACHE_MISSES: u64 = 3;
+
+    pub struct Counter;
+
+    impl Counter {
+        pub fn open(_config: u64) -> Option<Self> {
+            None
+        }
+
+        pub fn reset(&self) {}
+        pub fn enable(&self) {}
+        pub fn disable(&self) {}
+
+        pub fn read(&self) -> u64 {
+            0
+        }
+    }
+}
+
+/// Retired instructions, cycles and cache misses of the calling thread
+pub struct PerfCounters {
+    instructions: sys::Counter,
+    cycles: sys::Counter,
+    cache_misses: sys::Counter,
+}
+
+impl PerfCounters {
+    /// Open all counters, returns `None` if any of them is unavailable
+    pub fn new() -> Option<Self> {
+        Some(PerfCounters {
+            instructions: sys::Counter::open(sys::PERF_COUNT_HW_INSTRUCTIONS)?,
+            cycles: sys::Counter::open(sys::PERF_COUNT_HW_CPU_CYCLES)?,
+            cache_misses: sys::Counter::open(sys::PERF_COUNT_HW_CACHE_MISSES)?,
+        })
+    }
+
+    fn counters(&self) -> [&sys::Counter; 3] {
+        [&self.instructions, &self.cycles, &self.cache_misses]
+    }
+
+    fn reset_enable(&self) {
+        for counter in self.counters().iter() {
+            counter.reset();
+            counter.enable();
+        }
+    }
+
+    fn disable(&self) {
+        for counter in self.counters().iter() {
+            counter.disable();
+        }
+    }
+
+    fn read(&self) -> [u64; 3] {
+        [self.instructions.read(), self.cycles.read(), self.cache_misses.read()]
+    }
+}
+
+/// Returns true if hardware counters can be read on this machine
+pub fn available() -> bool {
+    PerfCounters::new().is_some()
+}
+
+/// Reset and start counting a measured region
+pub fn start() {
+    COUNTERS.with(|counters| {
+        if let Some(counters) = counters.borrow().as_ref() {
+            REGION_USED.with(|x| x.set(true));
+            counters.reset_enable();
+        }
+    });
+}
+
+/// Stop counting a measured region
+pub fn stop() {
+    COUNTERS.with(|counters| {
+        if let Some(counters) = counters.borrow().as_ref() {
+            counters.disable();
+        }
+    });
+}
+
+fn run_once(func: fn(ParamSamples), params: &ParamSamples) -> [u64; 3] {
+    let params = params.clone();
+    REGION_USED.with(|x| x.set(false));
+
+    COUNTERS.with(|counters| counters.borrow().as_ref().unwrap().reset_enable());
+    func(params);
+
+    COUNTERS.with(|counters| {
+        let counters = counters.borrow();
+        let counters = counters.as_ref().unwrap();
+
+        // the measured region already disabled the counters
+        if !REGION_USED.with(|x| x.get()) {
+            counters.disable();
+        }
+
+        counters.read()
+    })
+}
+
+/// Measure hardware counters of `func` with `params`, returns `None` if counters are unavailable
+pub fn measure_perf(func: fn(ParamSamples), params: &ParamSamples) -> Option<Stats> {
+    let counters = PerfCounters::new()?;
+    COUNTERS.with(|x| *x.borrow_mut() = Some(counters));
+
+    // warm up caches and the allocator
+    run_once(func, params);
+
+    let runs = (0..REPETITIONS)
+        .map(|_| run_once(func, params))
+        .collect::<Vec<_>>();
+
+    COUNTERS.with(|x| *x.borrow_mut() = None);
+
+    let metrics = [Metric::InstructionReads, Metric::Cycles, Metric::LLMisses];
+
+    let mut stats = Stats::default();
+    for (idx, metric) in metrics.iter().enumerate() {
+        let values = runs.iter().map(|x| x[idx] as f64).collect::<Vec<_>>();
+        let (median, mad) = median_mad(values);
+
+        stats.insert_noisy(*metric, median as u64, median_variance(mad, REPETITIONS));
+    }
+
+    Some(stats)
+}
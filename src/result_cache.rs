@@ -0,0 +1,156 @@
+//! Content-addressed cache of cachegrind results
+//!
+//! Entries are copies of cachegrind output files, keyed by the benchmark, the sample point and the
+//! valgrind configuration including its version. Next to each entry a manifest lists the hash of
+//! every function of the benchmark binary executed in the run. An entry is only reused while all
+//! of these functions are unchanged, so that changing one benchmark does not invalidate the results
+//! of the others. Constants and statics are not attributed to functions, the hash of all data
+//! sections is part of the key and changing any of them invalidates all entries.
+//!
+//! Code running before `start_instrumentation` is missing from the output of manually
+//! instrumented runs, although it may build their input. For these runs, and without a symbol
+//! table, the hash of the whole binary is part of the key instead and any rebuild invalidates
+//! their entries. Code moving after a rebuild can invalidate entries, but never keeps an outdated
+//! one.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use crate::error::Result;
+use crate::params::ParamSamples;
+use crate::symbols::{self, Symbols};
+use crate::valgrind::cachegrind::executed_functions;
+
+const CACHE_DIR: &str = "target/alco/cache";
+
+/// Hash the content of the benchmark binary
+pub fn hash_executable(executable: &str) -> Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    std::fs::read(executable)?.hash(&mut hasher);
+
+    Ok(hasher.finish())
+}
+
+/// Code of the benchmark binary which cached results depend on
+#[derive(Clone, Debug)]
+enum Code {
+    /// Hash of every function and the data, together with the hash of the whole binary
+    Symbols(Symbols, u64),
+    /// Hash of the whole binary, if it has no symbol table
+    Executable(u64),
+}
+
+/// Cache of cachegrind output files in `target/alco/cache`
+#[derive(Clone, Debug)]
+pub struct ResultCache {
+    /// Code of the benchmark binary, `None` if the cache is disabled
+    code: Option<Code>,
+}
+
+fn manifest_file(entry: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.functions", entry.display()))
+}
+
+impl ResultCache {
+    /// Open the cache for the benchmark binary `executable`
+    pub fn new(executable: &str) -> Result<Self> {
+        let hash = hash_executable(executable)?;
+        let code = match symbols::read(executable)? {
+            Some(symbols) => Code::Symbols(symbols, hash),
+            None => Code::Executable(hash),
+        };
+
+        Ok(ResultCache {
+            code: Some(code),
+        })
+    }
+
+    /// A cache which neither returns nor stores any results
+    pub fn disabled() -> Self {
+        ResultCache {
+            code: None,
+        }
+    }
+
+    /// Remove all cached results
+    pub fn clear() -> Result<()> {
+        match std::fs::remove_dir_all(CACHE_DIR) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Path of the entry for `run` of benchmark `i` named `benchmark` at `params`, `config` describes
+    /// everything else influencing the run. Returns `None` if the cache is disabled.
+    ///
+    /// The run is the benchmark itself or a helper run like its calibration, whose names are shared
+    /// between benchmarks. `manual` runs only measure the code after `start_instrumentation`.
+    pub fn entry(&self, benchmark: &str, i: usize, run: &str, params: &ParamSamples, config: &str, manual: bool) -> Option<PathBuf> {
+        let mut hasher = DefaultHasher::new();
+        match self.code.as_ref()? {
+            Code::Symbols(symbols, _) if !manual => symbols.data.hash(&mut hasher),
+            Code::Symbols(_, executable) | Code::Executable(executable) => executable.hash(&mut hasher),
+        }
+        benchmark.hash(&mut hasher);
+        i.hash(&mut hasher);
+        run.hash(&mut hasher);
+        config.hash(&mut hasher);
+
+        // the order of parameters is not stable between runs
+        let mut args = params.to_args();
+        args.sort();
+        args.hash(&mut hasher);
+        params.is_setup().hash(&mut hasher);
+        params.seed().hash(&mut hasher);
+
+        Some(PathBuf::from(format!("{}/{:016x}", CACHE_DIR, hasher.finish())))
+    }
+
+    /// Whether `entry` exists and every function executed in its run is unchanged
+    pub fn is_valid(&self, entry: &Path) -> bool {
+        if !entry.exists() {
+            return false;
+        }
+
+        let functions = match &self.code {
+            Some(Code::Symbols(symbols, _)) => &symbols.functions,
+            Some(Code::Executable(_)) => return true,
+            None => return false,
+        };
+
+        let manifest = match std::fs::read_to_string(manifest_file(entry)) {
+            Ok(manifest) => manifest,
+            Err(_) => return false,
+        };
+
+        manifest.lines().all(|line| {
+            let parsed = line.split_once('\t')
+                .and_then(|(name, hash)| Some((name, u64::from_str_radix(hash, 16).ok()?)));
+
+            matches!(parsed, Some((name, hash)) if functions.get(name) == Some(&hash))
+        })
+    }
+
+    /// Store a copy of `output_file` in an entry, together with the hashes of the executed
+    /// functions
+    pub fn insert(&self, entry: &Path, output_file: &Path) -> Result<()> {
+        std::fs::create_dir_all(CACHE_DIR)?;
+
+        if let Some(Code::Symbols(Symbols { functions, .. }, _)) = &self.code {
+            // functions of shared libraries are not part of the binary
+            let manifest = executed_functions(output_file)?.into_iter()
+                .filter_map(|name| Some(format!("{}\t{:016x}\n", name, functions.get(&name)?)))
+                .collect::<String>();
+
+            std::fs::write(manifest_file(entry), manifest)?;
+        }
+
+        std::fs::copy(output_file, entry)?;
+
+        Ok(())
+    }
+}
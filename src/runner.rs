@@ -0,0 +1,831 @@
+//! Library-level API to estimate the complexity of a set of benchmarks
+//!
+//! Errors of a single benchmark are returned to the caller, who may report them and continue with
+//! the next benchmark.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    time::{Duration, Instant},
+};
+
+use crate::backend::Backend;
+use crate::budget::Budget;
+use crate::child;
+use crate::counter::measure_counters;
+use crate::error::{Error, Result};
+use crate::instrumentation;
+use crate::memory::measure_memory;
+use crate::metric::{Metric, Stats};
+use crate::model::{self, Model, Observation};
+use crate::options::Options;
+use crate::params::{ParamBuilder, ParamSamples, Sample, Scale, DISTRIBUTION_PARAM};
+use crate::perf::{self, measure_perf};
+use crate::pool;
+use crate::sampling::{self, Design};
+use crate::result_cache::{hash_executable, ResultCache};
+use crate::session::Session;
+use crate::valgrind::{
+    ValgrindConfig,
+    cachegrind::{output_file, parse_cachegrind_output, run_bench},
+    callgrind::{hot_functions, run_callgrind},
+    dhat::{run_dhat, DhatStats},
+};
+use crate::wallclock::measure_wallclock;
+
+/// A benchmark with its name, function and parameter space
+pub type Benchmark<'a> = (&'static str, fn(ParamSamples), ParamBuilder<'a>);
+
+/// Metric of a dhat run with its name
+type DhatMetric = (&'static str, fn(&DhatStats) -> u64);
+
+/// Parameter which is still increased during the seeding, with its results, current step and
+/// minimal stride
+type Seeding<'a> = (&'a str, Vec<(usize, u64)>, usize, usize);
+
+/// Sampling parameters
+const NUM_SEEDING_STEPS: usize = 10;
+const NUM_STEPS: usize = 30;
+const MIN_CHANGE: u64 = 50;
+
+/// Model estimation parameters
+const BEAM_SIZE: usize = 4;
+const MAX_INTERACTIONS: usize = 3;
+
+/// Active sampling parameters, see `Design::Active`
+const ACTIVE_POOL_SIZE: usize = 1024;
+/// Difference in the Bayesian information criterion by which the best model has to beat the
+/// runner-up, more than 10 is considered very strong evidence
+const DOMINANCE: f64 = 10.0;
+
+/// Runs benchmarks of the executable in child processes and estimates their complexity
+pub struct Runner {
+    executable: String,
+    options: Options,
+    config: Option<ValgrindConfig>,
+    backend: Backend,
+    executable_hash: u64,
+    results: ResultCache,
+}
+
+impl Runner {
+    /// Create a runner for the benchmark binary `executable` from command line arguments
+    ///
+    /// Falls back to wall-clock measurements if valgrind is not available.
+    pub fn from_args<I: Iterator<Item = String>>(executable: String, args: I) -> Result<Self> {
+        let options = Options::from_args(args);
+
+        let config = ValgrindConfig::detect().unwrap_or_else(|e| {
+            println!("Unable to detect valgrind: {}", e);
+            None
+        });
+        let backend = match (options.backend, &config) {
+            (Backend::Cachegrind, None) => {
+                println!("Valgrind is not available, falling back to wall-clock measurements.");
+                Backend::WallClock
+            }
+            (Backend::Perf, config) if !perf::available() => {
+                println!("Hardware counters are not available, falling back to {} measurements.", if config.is_some() { "cachegrind" } else { "wall-clock" });
+                if config.is_some() { Backend::Cachegrind } else { Backend::WallClock }
+            }
+            (backend, _) => backend,
+        };
+
+        if options.clear_cache {
+            ResultCache::clear()?;
+        }
+
+        let executable_hash = hash_executable(&executable)?;
+        let results = if options.cache_results {
+            ResultCache::new(&executable)?
+        } else {
+            ResultCache::disabled()
+        };
+
+        Ok(Runner {
+            executable,
+            options,
+            config,
+            backend,
+            executable_hash,
+            results,
+        })
+    }
+
+    /// Estimate the complexity of benchmark `i` and print the models
+    pub fn run_benchmark(&self, benches: &[&Benchmark], i: usize) -> Result<()> {
+        let (name, func, param_builder) = benches[i];
+        let (executable, options, backend, config, results) = (&self.executable, &self.options, self.backend, &self.config, &self.results);
+
+        println!("{}", name);
+
+        // with manual instrumentation only the measured region is counted at every sample point,
+        // otherwise subtract the cost of a setup run at the lower bound
+        let instrumented = match (backend, config) {
+            (Backend::Cachegrind, Some(config)) => param_builder.has_manual_instrumentation() && config.client_requests,
+            _ => param_builder.has_manual_instrumentation(),
+        };
+        if param_builder.has_manual_instrumentation() && !instrumented {
+            println!("Manual instrumentation requires valgrind 3.22 or newer, falling back to a calibration run.");
+        }
+
+        let cache = param_builder.cache().unwrap_or(&options.cache);
+
+        // in-process backends run the benchmark in this process
+        instrumentation::set_manual(param_builder.has_manual_instrumentation());
+
+        // a session of an interrupted run can only be resumed with the same binary and settings
+        let mut hasher = DefaultHasher::new();
+        self.executable_hash.hash(&mut hasher);
+        format!("{:?} {:?} {:?} {}", backend, config, cache, instrumented).hash(&mut hasher);
+        let session = Session::open(name, hasher.finish())?;
+
+        // measure a single sample point with the selected backend, also returns whether the
+        // result was cached or measured in an interrupted session
+        let measure = |params: &ParamSamples, run: &str| -> Result<(Stats, bool)> {
+            if let Some(stats) = session.get(run, params) {
+                return Ok((stats, true));
+            }
+
+            match (backend, config) {
+                (Backend::Cachegrind, Some(config)) => {
+                    // a different valgrind version may simulate differently
+                    let key = format!("{:?} {:?} {:?} {}", config.version, config, cache.for_params(params), instrumented);
+                    let entry = results.entry(name, i, run, params, &key, instrumented);
+                    if let Some(entry) = entry.as_ref().filter(|x| results.is_valid(x)) {
+                        return Ok((Stats::from_cachegrind(&parse_cachegrind_output(entry)?), true));
+                    }
+
+                    let (stats, _old_stats) = run_bench(config, cache, executable, i, params, run, !instrumented)?;
+                    if let Some(entry) = &entry {
+                        results.insert(entry, &output_file(run, params))?;
+                    }
+
+                    Ok((Stats::from_cachegrind(&stats), false))
+                }
+                (Backend::Perf, _) => Ok((measure_perf(*func, params).ok_or(Error::CountersUnavailable)?, false)),
+                _ => Ok((measure_wallclock(*func, params), false)),
+            }
+        };
+
+        // the first supported metric guides the sampling
+        let mut metrics = param_builder.metrics().iter()
+            .copied()
+            .filter(|metric| backend.supports(*metric, config.as_ref()))
+            .collect::<Vec<_>>();
+
+        if metrics.len() < param_builder.metrics().len() {
+            println!("Some metrics are not supported by the {:?} backend and are skipped.", backend);
+        }
+        if metrics.is_empty() {
+            metrics.push(backend.default_metric());
+        }
+
+        // the calibration and seeding start at the lower bound
+        if !param_builder.is_valid(&param_builder.lower_bound()) {
+            return Err(Error::InvalidLowerBound);
+        }
+
+        let mut budget = Budget::new(options.timeout, options.budget);
+        child::set_timeout(Some(budget.deadline(1)));
+
+        let calibration = if instrumented {
+            None
+        } else {
+            let params = param_builder.lower_bound().setup_run(true);
+            let (calibration, _) = measure(&params, "alco_calibration")?;
+            session.record("alco_calibration", &params, &calibration)?;
+
+            Some(calibration)
+        };
+
+        // cost of a metric at a sample point, capped if smaller than the calibration
+        let delta = |stats: &Stats, metric: Metric| -> Option<u64> {
+            let base = match &calibration {
+                Some(calibration) => calibration.get(metric)?,
+                None => 0,
+            };
+
+            Some(stats.get(metric)?.saturating_sub(base))
+        };
+
+        // most instructions of a finished run, a lower bound of runs stopped at the full time limit
+        let mut observed = Stats::default();
+
+        // in-process backends measure one sample point at a time
+        let jobs = if backend.in_process() { 1 } else { options.jobs };
+        let repetitions = param_builder.num_repetitions();
+
+        let dataset = explore(
+            param_builder,
+            &session,
+            jobs,
+            |batch| {
+                if budget.exhausted() {
+                    println!("  Instruction budget exhausted, skipping the remaining sample points.");
+                    return Ok(None);
+                }
+
+                // every sample point is measured once per seed
+                let runs = batch.iter()
+                    .flat_map(|params| (0..repetitions).map(move |seed| params.clone().with_seed(seed as u64)))
+                    .collect::<Vec<_>>();
+
+                let deadline = budget.deadline(runs.len().min(jobs));
+                child::set_timeout(Some(deadline));
+
+                let results = pool::map(jobs, &runs, |params| {
+                    let start = Instant::now();
+                    let result = measure(params, name);
+
+                    (result, start.elapsed())
+                });
+
+                let mut repeated = Vec::new();
+                for (params, (result, elapsed)) in runs.iter().zip(results) {
+                    let stats = match result {
+                        // cached runs do not count towards the budget
+                        Ok((stats, true)) => {
+                            if !stats.is_censored() {
+                                observed.max(&instructions(&stats));
+                            }
+                            stats
+                        }
+                        // in-process backends cannot be interrupted, stop extending the parameter anyway
+                        Ok((stats, _)) if run_time(&stats, elapsed) > deadline => {
+                            budget.record(&stats, elapsed);
+                            stats.censored()
+                        }
+                        Ok((stats, _)) => {
+                            budget.record(&stats, elapsed);
+                            observed.max(&instructions(&stats));
+                            stats
+                        }
+                        // a run stopped at the full time limit executed at least as many instructions as
+                        // any finished run, a shortened deadline may stop it before finished runs ended
+                        Err(Error::Timeout(..)) => {
+                            let mut stats = budget.lower_bound(elapsed);
+                            if deadline >= options.timeout {
+                                stats.max(&observed);
+                            }
+                            budget.record_stopped(elapsed);
+
+                            stats.censored()
+                        }
+                        Err(e) => return Err(e),
+                    };
+
+                    if stats.is_censored() {
+                        println!("  {}: exceeded the limit of {:?}, costs recorded as lower bound", params, deadline);
+                    }
+
+                    session.record(name, params, &stats)?;
+
+                    repeated.push(stats);
+                }
+
+                let measured = repeated.chunks(repetitions)
+                    .map(Stats::combine)
+                    .map(|stats| (delta(&stats, metrics[0]).unwrap_or(0), stats))
+                    .collect();
+
+                Ok(Some(measured))
+            },
+            NUM_SEEDING_STEPS,
+            NUM_STEPS,
+            MIN_CHANGE,
+        )?;
+
+        child::set_timeout(Some(options.timeout));
+
+        // stopped runs would time out again in the remaining passes
+        let sample_points = dataset.iter()
+            .filter(|x| !x.1.is_censored())
+            .map(|x| x.0.clone())
+            .collect::<Vec<_>>();
+
+        for metric in &metrics {
+            let deviation = if repetitions > 1 {
+                fit_deviation(*metric, &dataset, repetitions)
+            } else {
+                None
+            };
+
+            // weight noisy measurements by their inverse relative variance, the fit already
+            // compares relative residuals
+            let base_variance = calibration.as_ref().map(|x| x.variance(*metric)).unwrap_or(0.0);
+            // stopped runs may lack a metric if no run finished before them
+            let dataset = dataset.iter()
+                .filter_map(|(params, stats)| {
+                    let cost = delta(stats, *metric)?;
+                    let variance = stats.variance(*metric) + base_variance;
+                    let weight = 1.0 / (1.0 + variance / (cost.max(1) as f64).powi(2));
+
+                    Some(Observation {
+                        params: params.clone(),
+                        cost,
+                        weight: weight as f32,
+                        censored: stats.is_censored(),
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            if dataset.is_empty() {
+                println!("  {}: not measured", metric.name());
+                continue;
+            }
+
+            if let Some(distributions) = param_builder.distributions() {
+                report_distributions(*metric, dataset, &distributions);
+                continue;
+            }
+
+            // estimate an additive model with beam-search and limited interactions between terms
+            let estimation = model::fit_weighted_additive(dataset, BEAM_SIZE, MAX_INTERACTIONS);
+
+            // print complexity estimation (may write to file in future)
+            println!("  {}: {}", metric.name(), estimation);
+
+            if let Some((deviation, spread)) = deviation {
+                println!("  {} (std. deviation): {} ({:.1}% of the cost on average)", metric.name(), deviation, spread * 100.0);
+            }
+        }
+
+        // estimate a model for every user-defined counter, if the benchmark reports any
+        let counters = match sample_points.first() {
+            Some(params) => measure_counters(executable, i, params, name)?,
+            None => Default::default(),
+        };
+
+        if !counters.is_empty() {
+            let stats = pool::map(options.jobs, &sample_points, |params| measure_counters(executable, i, params, name))
+                .into_iter()
+                .collect::<Result<Vec<_>>>()?;
+
+            let mut names = stats[0].keys().cloned().collect::<Vec<_>>();
+            names.sort();
+
+            for counter in names {
+                let dataset = sample_points.iter().zip(&stats)
+                    .map(|(params, stats)| (params.clone(), stats.get(&counter).copied().unwrap_or(0)))
+                    .collect::<Vec<_>>();
+
+                let estimation = model::fit_greedy_additive(dataset, BEAM_SIZE, MAX_INTERACTIONS);
+                println!("  counter `{}`: {}", counter, estimation);
+            }
+        }
+
+        if options.memory {
+            // the counting allocator respects manual instrumentation, massif has to subtract the
+            // heap usage of a setup run
+            let calibration = measure_memory(config.as_ref(), executable, i, &param_builder.lower_bound().setup_run(true), "alco_calibration")?;
+
+            match calibration {
+                Some(calibration) => {
+                    let stats = pool::map(options.jobs, &sample_points, |params| measure_memory(config.as_ref(), executable, i, params, name))
+                        .into_iter()
+                        .collect::<Result<Option<Vec<_>>>>()?
+                        .unwrap_or_default();
+
+                    let uses_massif = calibration.total_bytes.is_none();
+                    if uses_massif {
+                        println!("  `CountingAllocator` is not installed, measuring peak heap size with massif.");
+                    }
+
+                    let peak_calibration = if instrumented && !uses_massif { 0 } else { calibration.peak_bytes };
+                    let dataset = sample_points.iter().zip(&stats)
+                        .map(|(params, stats)| (params.clone(), stats.peak_bytes.saturating_sub(peak_calibration)))
+                        .collect::<Vec<_>>();
+
+                    let estimation = model::fit_greedy_additive(dataset, BEAM_SIZE, MAX_INTERACTIONS);
+                    println!("  peak heap bytes: {}", estimation);
+
+                    if !uses_massif {
+                        let total_calibration = if instrumented { 0 } else { calibration.total_bytes.unwrap_or(0) };
+                        let dataset = sample_points.iter().zip(&stats)
+                            .map(|(params, stats)| (params.clone(), stats.total_bytes.unwrap_or(0).saturating_sub(total_calibration)))
+                            .collect::<Vec<_>>();
+
+                        let estimation = model::fit_greedy_additive(dataset, BEAM_SIZE, MAX_INTERACTIONS);
+                        println!("  total heap bytes: {}", estimation);
+                    }
+                }
+                None => println!("Measuring heap usage requires `CountingAllocator` or valgrind."),
+            }
+        }
+
+        // the remaining measurements require valgrind
+        let config = match config {
+            Some(config) => config,
+            None => {
+                if options.dhat || options.callgrind {
+                    println!("Measuring heap allocations and per-function costs requires valgrind.");
+                }
+                return session.finish();
+            }
+        };
+
+        if options.dhat && !config.dhat {
+            println!("Measuring heap allocations requires valgrind 3.15 or newer.");
+        } else if options.dhat {
+            // DHAT cannot toggle instrumentation, so subtract the allocations of a setup run
+            let calibration = run_dhat(config, executable, i, &param_builder.lower_bound().setup_run(true), "alco_calibration")?;
+
+            let stats = pool::map(options.jobs, &sample_points, |params| run_dhat(config, executable, i, params, name))
+                .into_iter()
+                .collect::<Result<Vec<_>>>()?;
+
+            let metrics: [DhatMetric; 3] = [
+                ("allocated bytes", |x| x.total_bytes),
+                ("allocations", |x| x.total_blocks),
+                ("peak live bytes", |x| x.peak_bytes),
+            ];
+
+            for (metric, value) in metrics.iter() {
+                let dataset = sample_points.iter().zip(&stats)
+                    .map(|(params, stats)| (params.clone(), value(stats).saturating_sub(value(&calibration))))
+                    .collect::<Vec<_>>();
+
+                let estimation = model::fit_greedy_additive(dataset, BEAM_SIZE, MAX_INTERACTIONS);
+
+                println!("  {}: {}", metric, estimation);
+            }
+        }
+
+        if options.callgrind {
+            // run every sample point once more under callgrind and estimate a model of the
+            // inclusive cost for each of the functions with highest self cost
+            let stats = pool::map(options.jobs, &sample_points, |params| run_callgrind(config, executable, i, params, name, !instrumented))
+                .into_iter()
+                .collect::<Result<Vec<_>>>()?;
+
+            for function in hot_functions(&stats, options.num_functions) {
+                let dataset = sample_points.iter().zip(&stats)
+                    .map(|(params, stats)| {
+                        let cost = stats.functions.get(&function).copied().unwrap_or(0);
+                        (params.clone(), cost)
+                    })
+                    .collect::<Vec<_>>();
+
+                let total = dataset.iter().map(|x| x.1).sum::<u64>() as f64;
+                let share = total / stats.iter().map(|x| x.instruction_reads).sum::<u64>() as f64;
+
+                let estimation = model::fit_greedy_additive(dataset, BEAM_SIZE, MAX_INTERACTIONS);
+
+                println!("  `{}` ({:.1}% of instructions): {}", function, share * 100.0, estimation);
+            }
+        }
+
+        session.finish()
+    }
+}
+
+/// Fit a model for every input distribution and report the best, average and worst case
+///
+/// Distributions are ranked by their total cost over the sample points measured with all of them.
+/// The average case is fitted to the mean cost of all distributions at these sample points.
+fn report_distributions(metric: Metric, dataset: Vec<Observation>, distributions: &[String]) {
+    // sample points without the distribution, with the cost of every distribution
+    let mut points: HashMap<String, Vec<Option<&Observation>>> = HashMap::new();
+    for observation in &dataset {
+        let distribution = observation.params.distribution()
+            .and_then(|x| distributions.iter().position(|y| y == x));
+
+        if let Some(index) = distribution {
+            let prefix = format!("{}§", DISTRIBUTION_PARAM);
+            let mut key = observation.params.to_args();
+            key.retain(|x| !x.starts_with(&prefix));
+            key.sort();
+
+            points.entry(key.join(" ")).or_insert_with(|| vec![None; distributions.len()])[index] = Some(observation);
+        }
+    }
+
+    let complete = points.values()
+        .filter_map(|x| x.iter().cloned().collect::<Option<Vec<_>>>())
+        .collect::<Vec<_>>();
+
+    println!("  {}:", metric.name());
+
+    let models = distributions.iter()
+        .map(|distribution| {
+            let dataset = dataset.iter()
+                .filter(|x| x.params.distribution() == Some(distribution.as_str()))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            let estimation = model::fit_weighted_additive(dataset, BEAM_SIZE, MAX_INTERACTIONS);
+            println!("    {:<16}{}", distribution, estimation);
+
+            estimation
+        })
+        .collect::<Vec<_>>();
+
+    if complete.is_empty() {
+        println!("    no sample point was measured with every distribution");
+        return;
+    }
+
+    let totals = (0..distributions.len())
+        .map(|index| complete.iter().map(|x| x[index].cost).sum::<u64>())
+        .collect::<Vec<_>>();
+
+    let best = (0..totals.len()).min_by_key(|x| totals[*x]).unwrap();
+    let worst = (0..totals.len()).max_by_key(|x| totals[*x]).unwrap();
+
+    let average = complete.iter()
+        .map(|observations| Observation {
+            params: observations[0].params.clone(),
+            cost: observations.iter().map(|x| x.cost).sum::<u64>() / observations.len() as u64,
+            weight: observations.iter().map(|x| x.weight).sum::<f32>() / observations.len() as f32,
+            censored: observations.iter().any(|x| x.censored),
+        })
+        .collect::<Vec<_>>();
+
+    let estimation = model::fit_weighted_additive(average, BEAM_SIZE, MAX_INTERACTIONS);
+
+    println!("    {:<16}{} ({})", "best case", models[best], distributions[best]);
+    println!("    {:<16}{}", "average case", estimation);
+    println!("    {:<16}{} ({})", "worst case", models[worst], distributions[worst]);
+}
+
+/// Fit a model of the standard deviation of a metric between the seeds of a sample point
+///
+/// The variance of the combined statistics is the variance of the mean over `repetitions` runs,
+/// scaling it back gives the spread of a single run. Stopped runs do not bound the spread and are
+/// skipped. Returns the model with the mean spread relative to the cost, or `None` if no sample
+/// point was measured completely.
+fn fit_deviation(metric: Metric, dataset: &[(ParamSamples, Stats)], repetitions: usize) -> Option<(Model, f64)> {
+    let dataset = dataset.iter()
+        .filter(|(_, stats)| !stats.is_censored())
+        .filter_map(|(params, stats)| Some((params, stats.get(metric)?, (stats.variance(metric) * repetitions as f64).sqrt())))
+        .collect::<Vec<_>>();
+
+    if dataset.is_empty() {
+        return None;
+    }
+
+    let spread = dataset.iter()
+        .map(|(_, cost, deviation)| deviation / (*cost).max(1) as f64)
+        .sum::<f64>() / dataset.len() as f64;
+
+    let dataset = dataset.into_iter()
+        .map(|(params, _, deviation)| Observation {
+            params: params.clone(),
+            cost: deviation as u64,
+            weight: 1.0,
+            censored: false,
+        })
+        .collect();
+
+    Some((model::fit_weighted_additive(dataset, BEAM_SIZE, MAX_INTERACTIONS), spread))
+}
+
+/// Only the instruction reads of `stats`, other metrics need not grow with the run time
+fn instructions(stats: &Stats) -> Stats {
+    let mut instructions = Stats::default();
+    if let Some(value) = stats.get(Metric::InstructionReads) {
+        instructions.insert(Metric::InstructionReads, value);
+    }
+
+    instructions
+}
+
+/// Duration of a single run, the measured wall-clock time if available or otherwise the time of
+/// the whole measurement
+fn run_time(stats: &Stats, elapsed: Duration) -> Duration {
+    stats.get(Metric::WallTime)
+        .map(Duration::from_nanos)
+        .unwrap_or(elapsed)
+}
+
+/// Explore the parameter space of a benchmark and measure every sample point
+///
+/// `measure` returns the cost guiding the sampling together with all statistics for a batch of
+/// sample points, which may be measured in parallel. Each parameter is first increased from its
+/// lower bound until the cost changes significantly. The steps of a parameter depend on each
+/// other, but all parameters are increased in lockstep and measured in the same batch. Then random
+/// combinations of these sample points are measured in batches of `batch_size`.
+///
+/// A parameter is not extended further once a run is stopped early, and the exploration ends if
+/// `measure` returns `None`. The chosen combinations are persisted in the session, or taken from
+/// it if the session of an interrupted run is resumed.
+fn explore<'a, F>(
+    param_builder: &ParamBuilder<'a>,
+    session: &Session,
+    batch_size: usize,
+    mut measure: F,
+    num_seeding_steps: usize,
+    num_steps: usize,
+    min_change: u64,
+) -> Result<Vec<(ParamSamples, Stats)>>
+where
+    F: FnMut(&[ParamSamples]) -> Result<Option<Vec<(u64, Stats)>>>,
+{
+    // fit an unimodal polynomial to each term and increase such that the change in
+    // instructions is significant
+    //
+    let mut samples: Vec<(&str, Vec<usize>)> = Vec::new();
+    let mut dataset = Vec::new();
+    let mut observations = Vec::new();
+
+    let mut seeding: Vec<Seeding> = Vec::new();
+    for (param_name, param) in param_builder.params() {
+        // if the parameter is a item set, then just collect all indices as samples.
+        // we have to try out every item anyways.
+        if let Some(length) = param.num_items() {
+            samples.push((param_name, (0..length).collect()));
+            continue;
+        }
+
+        // logarithmic ranges are short, spread the seeding steps over all of their values
+        let stride = match (param.scale(), param.num_steps()) {
+            (Scale::Logarithmic, Some(num)) => num.div_ceil(num_seeding_steps),
+            _ => 1,
+        };
+
+        seeding.push((param_name, Vec::new(), 0, stride.max(1)));
+    }
+
+    for _ in 0..num_seeding_steps {
+        // stop increasing parameters at the end of their range, or if no sample point satisfies
+        // the constraints
+        let mut batch = Vec::new();
+        for seed in std::mem::take(&mut seeding) {
+            match param_builder.seeding_sample(seed.0, seed.2) {
+                Some(params) => {
+                    batch.push(params);
+                    seeding.push(seed);
+                }
+                None => samples.push((seed.0, seed.1.into_iter().map(|x| x.0).collect())),
+            }
+        }
+
+        if seeding.is_empty() {
+            break;
+        }
+
+        // pass params and calculate stats
+        let measured = match measure(&batch)? {
+            Some(measured) => measured,
+            None => return Ok(dataset),
+        };
+
+        let mut finished = Vec::new();
+        for (index, (params, (cost, stats))) in batch.into_iter().zip(measured).enumerate() {
+            let (_, results, current_step, stride) = &mut seeding[index];
+            let censored = stats.is_censored();
+
+            results.push((*current_step, cost));
+            observations.push(Observation { censored, ..Observation::new(params.clone(), cost) });
+            dataset.push((params, stats));
+
+            match model::estimate_stepsize(results, min_change) {
+                x if x > 0 && !censored => *current_step += x.max(*stride),
+                _ => finished.push(index),
+            }
+        }
+
+        for index in finished.into_iter().rev() {
+            let (param_name, results, _, _) = seeding.remove(index);
+            samples.push((param_name, results.into_iter().map(|x| x.0).collect()));
+        }
+    }
+
+    for (param_name, results, _, _) in seeding {
+        samples.push((param_name, results.into_iter().map(|x| x.0).collect()));
+    }
+
+    // sample with combinations of sample points estimated in previous step. The combinations are
+    // chosen by the experimental design and the estimated instruction counter saved into a dataset.
+    // Combinations violating a constraint are never sampled. Every chosen combination is measured
+    // with all input distributions, so that their costs can be compared.
+    let distributions = samples.iter()
+        .position(|x| x.0 == DISTRIBUTION_PARAM)
+        .map(|index| samples.remove(index));
+
+    let variants = |comb: &[usize]| {
+        let indices: Vec<(&str, usize)> = samples.iter().zip(comb).map(|(a, b)| (a.0, a.1[*b])).collect();
+        let variants = match &distributions {
+            Some((param_name, items)) => items.iter()
+                .map(|item| indices.iter().cloned().chain(std::iter::once((*param_name, *item))).collect())
+                .collect(),
+            None => vec![indices],
+        };
+
+        variants.into_iter()
+            .map(|indices| param_builder.from_indices(indices)
+                .expect("Sample points are in range of their parameters"))
+            .filter(|params| param_builder.is_valid(params))
+            .collect::<Vec<_>>()
+    };
+
+    let axes = samples.iter()
+        .map(|(param_name, steps)| {
+            let param = &param_builder.params()[param_name];
+            let values = steps.iter()
+                .map(|step| match param.for_step(*step) {
+                    Some(Sample::Usize(x)) => Some(x as f64),
+                    Some(Sample::Float(x)) => Some(x as f64),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            sampling::axis(&values, param.is_ordered())
+        })
+        .collect::<Vec<_>>();
+
+    if param_builder.design() == Some(Design::Active) {
+        let pool = sampling::choose(Some(Design::Active), &axes, ACTIVE_POOL_SIZE, &mut rand::thread_rng(), |comb| !variants(comb).is_empty())
+            .into_iter()
+            .map(|comb| variants(&comb))
+            .collect();
+
+        explore_active(pool, batch_size, measure, num_steps, &mut dataset, observations)?;
+
+        return Ok(dataset);
+    }
+
+    let combs = match session.combinations() {
+        Some(combs) => combs.iter().cloned().map(|x| param_builder.complete(x)).collect(),
+        None => {
+            let combs = sampling::choose(param_builder.design(), &axes, num_steps, &mut rand::thread_rng(), |comb| !variants(comb).is_empty())
+                .into_iter()
+                .flat_map(|comb| variants(&comb))
+                .collect::<Vec<_>>();
+
+            session.record_combinations(&combs)?;
+            combs
+        }
+    };
+
+    for batch in combs.chunks(batch_size.max(1)) {
+        // pass params and calculate stats
+        let measured = match measure(batch)? {
+            Some(measured) => measured,
+            None => break,
+        };
+
+        // push current stats to dataset
+        for (params, (_, stats)) in batch.iter().zip(measured) {
+            dataset.push((params.clone(), stats));
+        }
+    }
+
+    Ok(dataset)
+}
+
+/// Measure combinations from `pool` where the best fitting models disagree most
+///
+/// Each round fits additive models to all observations and measures the `batch_size` combinations
+/// with the largest spread of the logarithmic predictions of the best models of the beam search.
+/// Sampling stops once the best model beats the runner-up by `DOMINANCE` in the information
+/// criterion, after `num_steps` combinations or if the pool is used up. The choice only depends on
+/// the measured costs, so a resumed session follows the same path.
+fn explore_active<F>(
+    mut pool: Vec<Vec<ParamSamples>>,
+    batch_size: usize,
+    mut measure: F,
+    num_steps: usize,
+    dataset: &mut Vec<(ParamSamples, Stats)>,
+    mut observations: Vec<Observation>,
+) -> Result<()>
+where
+    F: FnMut(&[ParamSamples]) -> Result<Option<Vec<(u64, Stats)>>>,
+{
+    let mut num_measured = 0;
+
+    while num_measured < num_steps && !pool.is_empty() {
+        let models = model::fit_best_additive(observations.clone(), BEAM_SIZE, MAX_INTERACTIONS, BEAM_SIZE);
+
+        if let [best, second, ..] = models.as_slice() {
+            if num_measured > 0 && best.score() + DOMINANCE < second.score() {
+                println!("  {} dominates after {} combinations, stopping the sampling.", best, num_measured);
+                break;
+            }
+        }
+
+        // stable sort, points with equal disagreement keep the order of the Sobol sequence
+        pool.sort_by(|a, b| {
+            model::disagreement(&models, &b[0]).total_cmp(&model::disagreement(&models, &a[0]))
+        });
+
+        let num = batch_size.max(1).min(num_steps - num_measured).min(pool.len());
+        let batch = pool.drain(..num).flatten().collect::<Vec<_>>();
+
+        // pass params and calculate stats
+        let measured = match measure(&batch)? {
+            Some(measured) => measured,
+            None => break,
+        };
+
+        for (params, (cost, stats)) in batch.into_iter().zip(measured) {
+            observations.push(Observation { censored: stats.is_censored(), ..Observation::new(params.clone(), cost) });
+            dataset.push((params, stats));
+        }
+
+        num_measured += num;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,287 @@
+//! Experimental designs choosing the combinations of sample points measured after seeding
+//!
+//! The seeding finds a list of candidate steps for every parameter. A design places points in the
+//! unit hypercube spanned by the parameters and maps each coordinate to the nearest candidate of its
+//! parameter. Ordered parameters are positioned in log-space, so that points spread over all orders
+//! of magnitude instead of clustering at the largest values.
+
+use std::collections::HashSet;
+
+use itertools::Itertools;
+use rand::{prelude::IteratorRandom, seq::SliceRandom, Rng};
+
+/// Strategy to choose combinations of candidate steps, see `ParamBuilder::set_design`
+///
+/// Without an explicit design, small grids are measured full factorial and larger ones with a
+/// Sobol sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Design {
+    /// Uniformly random combinations of the candidate steps
+    Random,
+    /// Latin hypercube, every parameter is divided into strata which are each sampled once
+    LatinHypercube,
+    /// Sobol low-discrepancy sequence, falls back to Halton for more than ten parameters
+    Sobol,
+    /// Halton low-discrepancy sequence
+    Halton,
+    /// Every combination of the candidate steps
+    FullFactorial,
+    /// Measure in batches and choose the next points where the best additive models of the beam
+    /// search disagree most, until one model dominates. Points are picked from a Sobol sequence.
+    Active,
+}
+
+/// Number of parameters for which Sobol direction numbers are available
+const SOBOL_DIMENSIONS: usize = 10;
+
+/// Primitive polynomials and initial direction numbers of the Sobol sequence from Joe and Kuo,
+/// as `(degree, coefficients, m)` for the dimensions following the first one
+const SOBOL_DIRECTIONS: [(usize, u32, &[u32]); SOBOL_DIMENSIONS - 1] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+];
+
+const SOBOL_BITS: usize = 32;
+
+/// Number of design points tried per requested combination before giving up, since points may map
+/// to the same combination or violate a constraint
+const MAX_ATTEMPTS: usize = 64;
+
+/// Position of every candidate of a parameter in `[0, 1]`
+///
+/// Ordered parameters are placed by the logarithm of their values, or linearly if a value is not
+/// positive. Categorical parameters and parameters without numeric values are spaced evenly.
+pub fn axis(values: &[Option<f64>], ordered: bool) -> Vec<f64> {
+    let num = values.len();
+    let numeric = values.iter().cloned().collect::<Option<Vec<_>>>();
+
+    let values = match numeric {
+        Some(values) if ordered && num > 1 => values,
+        _ => return (0..num).map(|x| (x as f64 + 0.5) / num as f64).collect(),
+    };
+
+    let values = if values.iter().all(|x| *x > 0.0) {
+        values.iter().map(|x| x.ln()).collect()
+    } else {
+        values
+    };
+
+    let (min, max) = values.iter().fold((f64::MAX, f64::MIN), |(min, max), x| (min.min(*x), max.max(*x)));
+    if max <= min {
+        return vec![0.5; num];
+    }
+
+    values.iter().map(|x| (x - min) / (max - min)).collect()
+}
+
+/// Choose up to `num_points` distinct combinations of candidate indices
+///
+/// `axes` contains the positions of the candidates of every parameter, see `axis`. Combinations
+/// rejected by `accept` are skipped, e.g. if they violate a constraint. A full factorial design
+/// returns every accepted combination, regardless of `num_points`.
+pub fn choose<R, F>(design: Option<Design>, axes: &[Vec<f64>], num_points: usize, rng: &mut R, mut accept: F) -> Vec<Vec<usize>>
+where
+    R: Rng,
+    F: FnMut(&[usize]) -> bool,
+{
+    let grid_size = axes.iter().map(|x| x.len()).product::<usize>();
+    if grid_size == 0 {
+        return Vec::new();
+    }
+
+    let design = design.unwrap_or(if grid_size <= num_points {
+        Design::FullFactorial
+    } else {
+        Design::Sobol
+    });
+
+    let grid = || axes.iter().map(|x| 0..x.len()).multi_cartesian_product();
+
+    match design {
+        Design::FullFactorial => return grid().filter(|x| accept(x)).collect(),
+        Design::Random => return grid().filter(|x| accept(x)).choose_multiple(rng, num_points),
+        _ => {}
+    }
+
+    let mut chosen = Vec::new();
+    let mut seen = HashSet::new();
+    let mut points = Vec::new();
+
+    for index in 0..num_points * MAX_ATTEMPTS {
+        if chosen.len() >= num_points || seen.len() >= grid_size {
+            break;
+        }
+
+        let point = match design {
+            Design::Sobol | Design::Active if axes.len() <= SOBOL_DIMENSIONS => sobol(index as u32, axes.len()),
+            Design::Sobol | Design::Active | Design::Halton => halton(index, axes.len()),
+            // draw a new hypercube whenever the previous one is used up
+            _ => {
+                if points.is_empty() {
+                    points = latin_hypercube(num_points, axes.len(), rng);
+                }
+                points.pop().unwrap()
+            }
+        };
+
+        let comb = axes.iter().zip(point)
+            .map(|(axis, x)| nearest(axis, x))
+            .collect::<Vec<_>>();
+
+        if seen.insert(comb.clone()) && accept(&comb) {
+            chosen.push(comb);
+        }
+    }
+
+    chosen
+}
+
+/// Index of the candidate closest to position `x`
+fn nearest(axis: &[f64], x: f64) -> usize {
+    (0..axis.len())
+        .min_by(|a, b| (axis[*a] - x).abs().total_cmp(&(axis[*b] - x).abs()))
+        .unwrap()
+}
+
+/// `num_points` points of a Latin hypercube in `dimensions` dimensions
+fn latin_hypercube<R: Rng>(num_points: usize, dimensions: usize, rng: &mut R) -> Vec<Vec<f64>> {
+    let mut points = vec![Vec::with_capacity(dimensions); num_points];
+
+    for _ in 0..dimensions {
+        let mut strata = (0..num_points).collect::<Vec<_>>();
+        strata.shuffle(rng);
+
+        for (point, stratum) in points.iter_mut().zip(strata) {
+            point.push((stratum as f64 + rng.gen::<f64>()) / num_points as f64);
+        }
+    }
+
+    points
+}
+
+/// Point `index` of the Sobol sequence in up to `SOBOL_DIMENSIONS` dimensions
+fn sobol(index: u32, dimensions: usize) -> Vec<f64> {
+    (0..dimensions)
+        .map(|dimension| {
+            let directions = sobol_directions(dimension);
+            let x = (0..SOBOL_BITS)
+                .filter(|bit| (index >> bit) & 1 == 1)
+                .fold(0u32, |x, bit| x ^ directions[bit]);
+
+            x as f64 / (1u64 << SOBOL_BITS) as f64
+        })
+        .collect()
+}
+
+fn sobol_directions(dimension: usize) -> Vec<u32> {
+    let mut directions = vec![0u32; SOBOL_BITS];
+
+    if dimension == 0 {
+        for (bit, direction) in directions.iter_mut().enumerate() {
+            *direction = 1 << (SOBOL_BITS - 1 - bit);
+        }
+
+        return directions;
+    }
+
+    let (degree, coefficients, m) = SOBOL_DIRECTIONS[dimension - 1];
+    for bit in 0..SOBOL_BITS {
+        directions[bit] = if bit < degree {
+            m[bit] << (SOBOL_BITS - 1 - bit)
+        } else {
+            let mut direction = directions[bit - degree] ^ (directions[bit - degree] >> degree);
+            for k in 1..degree {
+                if (coefficients >> (degree - 1 - k)) & 1 == 1 {
+                    direction ^= directions[bit - k];
+                }
+            }
+            direction
+        };
+    }
+
+    directions
+}
+
+/// Point `index` of the Halton sequence, with the first primes as bases
+fn halton(index: usize, dimensions: usize) -> Vec<f64> {
+    primes(dimensions).into_iter()
+        .map(|base| {
+            let (mut x, mut scale, mut rest) = (0.0, 1.0, index);
+            while rest > 0 {
+                scale /= base as f64;
+                x += scale * (rest % base) as f64;
+                rest /= base;
+            }
+
+            x
+        })
+        .collect()
+}
+
+fn primes(num: usize) -> Vec<usize> {
+    (2..)
+        .filter(|x| (2..*x).take_while(|y| y * y <= *x).all(|y| x % y != 0))
+        .take(num)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn latin_hypercube_covers_every_stratum_once() {
+        let num_points = 17;
+        let points = latin_hypercube(num_points, 3, &mut StdRng::seed_from_u64(0));
+
+        for dimension in 0..3 {
+            let mut strata = points.iter()
+                .map(|point| (point[dimension] * num_points as f64) as usize)
+                .collect::<Vec<_>>();
+            strata.sort_unstable();
+
+            assert_eq!(strata, (0..num_points).collect::<Vec<_>>(), "dimension {}", dimension);
+        }
+    }
+
+    #[test]
+    fn sobol_starts_with_known_points() {
+        let points = (0..8).map(|index| sobol(index, 2)).collect::<Vec<_>>();
+
+        assert_eq!(points, vec![
+            vec![0.0, 0.0],
+            vec![0.5, 0.5],
+            vec![0.25, 0.75],
+            vec![0.75, 0.25],
+            vec![0.125, 0.625],
+            vec![0.625, 0.125],
+            vec![0.375, 0.375],
+            vec![0.875, 0.875],
+        ]);
+    }
+
+    #[test]
+    fn sobol_stratifies_every_dimension() {
+        // the first 2^k points of every dimension fall into distinct intervals of width 2^-k
+        let num_points = 64;
+        let points = (0..num_points).map(|index| sobol(index, SOBOL_DIMENSIONS)).collect::<Vec<_>>();
+
+        for dimension in 0..SOBOL_DIMENSIONS {
+            let mut strata = points.iter()
+                .map(|point| (point[dimension] * num_points as f64) as u32)
+                .collect::<Vec<_>>();
+            strata.sort_unstable();
+
+            assert_eq!(strata, (0..num_points).collect::<Vec<_>>(), "dimension {}", dimension);
+        }
+    }
+}
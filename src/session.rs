@@ -0,0 +1,248 @@
+//! Persisted state of a benchmark to resume interrupted sessions
+//!
+//! Every measured sample point and the chosen combinations are appended to a session file in
+//! `target/alco/sessions`. If the runner is interrupted, the next run of the same benchmark binary
+//! replays the file instead of measuring these sample points again. Since the seeding only depends
+//! on the measured costs, the replay follows the same path as the interrupted run. The file is
+//! removed once the benchmark finished.
+
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+};
+
+use crate::error::{Error, Result};
+use crate::metric::{Metric, Stats};
+use crate::params::ParamSamples;
+
+/// Measured sample points and chosen combinations of an interrupted run
+pub struct Session {
+    file: PathBuf,
+    samples: HashMap<String, Stats>,
+    combinations: Option<Vec<ParamSamples>>,
+}
+
+/// Key of a sample point, independent of the order of parameters
+fn sample_key(name: &str, params: &ParamSamples) -> String {
+    let mut args = params.to_args();
+    args.sort();
+
+    format!("{}\t{}\t{}\t{}", name, params.is_setup(), params.seed(), args.join(" "))
+}
+
+fn parse_params(is_setup: &str, seed: &str, args: &str) -> Result<ParamSamples> {
+    let is_setup = is_setup.parse()
+        .map_err(|_| parse_error(is_setup))?;
+    let seed = seed.parse()
+        .map_err(|_| parse_error(seed))?;
+
+    Ok(ParamSamples::from_string(is_setup, args.to_string())?.with_seed(seed))
+}
+
+fn format_stats(stats: &Stats) -> String {
+    let values = stats.iter()
+        .map(|(metric, value, variance)| format!("{}={}:{}", metric.name(), value, variance))
+        .collect::<Vec<_>>();
+
+    format!("{}\t{}", stats.is_censored(), values.join(","))
+}
+
+fn parse_stats(censored: &str, values: &str) -> Result<Stats> {
+    let mut stats = Stats::default();
+
+    for value in values.split(',').filter(|x| !x.is_empty()) {
+        let parsed = value.split_once('=').and_then(|(metric, value)| {
+            let (value, variance) = value.split_once(':')?;
+            Some((Metric::from_name(metric)?, value.parse().ok()?, variance.parse().ok()?))
+        });
+
+        match parsed {
+            Some((metric, value, variance)) => stats.insert_noisy(metric, value, variance),
+            None => return Err(parse_error(value)),
+        }
+    }
+
+    match censored {
+        "true" => Ok(stats.censored()),
+        "false" => Ok(stats),
+        _ => Err(parse_error(censored)),
+    }
+}
+
+fn parse_error(reason: &str) -> Error {
+    Error::ParseOutput("session".to_string(), reason.to_string())
+}
+
+impl Session {
+    /// Open the session of benchmark `name`
+    ///
+    /// `key` identifies the benchmark binary and configuration. A session file with a different key
+    /// belongs to an outdated run and is discarded.
+    pub fn open(name: &str, key: u64) -> Result<Self> {
+        let file = PathBuf::from(format!("target/alco/sessions/{}", name));
+        std::fs::create_dir_all(file.parent().unwrap())?;
+
+        let mut session = Session {
+            file,
+            samples: HashMap::new(),
+            combinations: None,
+        };
+
+        let header = format!("key\t{:016x}", key);
+        let content = std::fs::read_to_string(&session.file).unwrap_or_default();
+
+        if content.lines().next() != Some(header.as_str()) {
+            std::fs::write(&session.file, format!("{}\n", header))?;
+            return Ok(session);
+        }
+
+        session.parse_lines(content.split_once('\n').map(|x| x.1).unwrap_or_default())?;
+
+        if !session.samples.is_empty() {
+            println!("  Resuming session with {} measured sample points.", session.samples.len());
+        }
+
+        Ok(session)
+    }
+
+    /// Read the measured sample points and chosen combinations from the lines of a session file
+    /// following its header
+    fn parse_lines(&mut self, content: &str) -> Result<()> {
+        for line in content.split_inclusive('\n') {
+            // the last line may be incomplete if the runner was killed while writing
+            let line = match line.strip_suffix('\n') {
+                Some(line) => line,
+                None => break,
+            };
+            let fields = line.split('\t').collect::<Vec<_>>();
+
+            match fields.as_slice() {
+                ["sample", name, is_setup, seed, args, censored, values] => {
+                    let params = parse_params(is_setup, seed, args)?;
+                    let stats = parse_stats(censored, values)?;
+
+                    self.samples.insert(sample_key(name, &params), stats);
+                }
+                ["combination", is_setup, seed, args] => {
+                    let params = parse_params(is_setup, seed, args)?;
+
+                    self.combinations.get_or_insert_with(Vec::new).push(params);
+                }
+                _ => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn append(&self, line: String) -> Result<()> {
+        let mut file = OpenOptions::new().append(true).open(&self.file)?;
+        file.write_all(line.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Statistics of a sample point measured in the interrupted run
+    pub fn get(&self, name: &str, params: &ParamSamples) -> Option<Stats> {
+        self.samples.get(&sample_key(name, params)).cloned()
+    }
+
+    /// Persist the statistics of a measured sample point
+    pub fn record(&self, name: &str, params: &ParamSamples, stats: &Stats) -> Result<()> {
+        let key = sample_key(name, params);
+        if self.samples.contains_key(&key) {
+            return Ok(());
+        }
+
+        self.append(format!("sample\t{}\t{}\n", key, format_stats(stats)))
+    }
+
+    /// Combinations of sample points chosen in the interrupted run
+    pub fn combinations(&self) -> Option<&[ParamSamples]> {
+        self.combinations.as_deref()
+    }
+
+    /// Persist the chosen combinations of sample points
+    pub fn record_combinations(&self, combinations: &[ParamSamples]) -> Result<()> {
+        let lines = combinations.iter()
+            .map(|params| {
+                let mut args = params.to_args();
+                args.sort();
+
+                format!("combination\t{}\t{}\t{}\n", params.is_setup(), params.seed(), args.join(" "))
+            })
+            .collect::<String>();
+
+        self.append(lines)
+    }
+
+    /// Remove the session file of a finished benchmark
+    pub fn finish(self) -> Result<()> {
+        std::fs::remove_file(&self.file)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> Stats {
+        let mut stats = Stats::default();
+        stats.insert(Metric::InstructionReads, 12345);
+        stats.insert_noisy(Metric::WallTime, 678, 2.5);
+
+        stats.censored()
+    }
+
+    fn values(stats: &Stats) -> Vec<(&'static str, u64, f64)> {
+        let mut values = stats.iter().map(|(metric, value, variance)| (metric.name(), value, variance)).collect::<Vec<_>>();
+        values.sort_by(|a, b| a.0.cmp(b.0));
+
+        values
+    }
+
+    #[test]
+    fn stats_round_trip() {
+        let line = format_stats(&stats());
+        let (censored, values_line) = line.split_once('\t').unwrap();
+        let parsed = parse_stats(censored, values_line).unwrap();
+
+        assert!(parsed.is_censored());
+        assert_eq!(values(&parsed), values(&stats()));
+    }
+
+    #[test]
+    fn params_round_trip() {
+        let params = ParamSamples::from_string(false, "n§usize§10 x§float§0.5".to_string()).unwrap().with_seed(3);
+        let key = sample_key("sort", &params);
+
+        let fields = key.split('\t').collect::<Vec<_>>();
+        let parsed = parse_params(fields[1], fields[2], fields[3]).unwrap();
+
+        assert_eq!(sample_key("sort", &parsed), key);
+    }
+
+    #[test]
+    fn skips_truncated_last_line() {
+        let params = ParamSamples::from_string(false, "n§usize§10".to_string()).unwrap();
+        let line = format!("sample\t{}\t{}\n", sample_key("sort", &params), format_stats(&stats()));
+        let combination = "combination\tfalse\t0\tn§usize§20\n";
+
+        // killed while writing the values of the second sample
+        let content = format!("{}{}{}", line, combination, &line[..line.len() - 4]);
+        let mut session = Session {
+            file: PathBuf::new(),
+            samples: HashMap::new(),
+            combinations: None,
+        };
+        session.parse_lines(&content).unwrap();
+
+        assert_eq!(session.samples.len(), 1);
+        assert_eq!(values(&session.get("sort", &params).unwrap()), values(&stats()));
+        assert_eq!(session.combinations().unwrap()[0].get_usize("n"), Some(20));
+    }
+}
@@ -0,0 +1,146 @@
+//! Hashes of the machine code of every function in the benchmark binary
+//!
+//! Reads the symbol table of a 64-bit little-endian ELF file, which covers benchmark binaries on
+//! linux, the only platform valgrind supports well. Stripped binaries have no symbol table and
+//! other formats are not parsed, in both cases `read` returns `None`. Constants and statics are not
+//! attributed to functions, their sections are hashed as a whole.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    convert::{TryFrom, TryInto},
+    hash::{Hash, Hasher},
+};
+
+use crate::error::Result;
+
+const SHT_SYMTAB: u32 = 2;
+const SHT_NOBITS: u32 = 8;
+const STT_FUNC: u8 = 2;
+
+const SECTION_HEADER_SIZE: usize = 64;
+const SYMBOL_SIZE: usize = 24;
+
+/// Hash of the code of every function, by mangled symbol name
+pub type FunctionHashes = HashMap<String, u64>;
+
+/// Hashes of the code and data of a binary
+#[derive(Clone, Debug)]
+pub struct Symbols {
+    pub functions: FunctionHashes,
+    /// Hash of the read-only and initialized data sections
+    pub data: u64,
+}
+
+struct Section {
+    name: u32,
+    kind: u32,
+    addr: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+}
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn read_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn bytes(data: &[u8], offset: u64, size: u64) -> Option<&[u8]> {
+    let start = usize::try_from(offset).ok()?;
+    let end = start.checked_add(usize::try_from(size).ok()?)?;
+
+    data.get(start..end)
+}
+
+/// Whether a section holds constants or initial values of statics
+///
+/// Relocated data like vtables is skipped, it changes whenever the code it points to moves.
+fn is_data(name: &[u8]) -> bool {
+    let name = String::from_utf8_lossy(name);
+
+    [".rodata", ".data", ".tdata"].iter().any(|x| name == *x || name.starts_with(&format!("{}.", x)))
+        && !name.starts_with(".data.rel")
+}
+
+/// Hash the code of every function and the data of `executable`
+pub fn read(executable: &str) -> Result<Option<Symbols>> {
+    let data = std::fs::read(executable)?;
+
+    Ok(parse(&data))
+}
+
+fn parse(data: &[u8]) -> Option<Symbols> {
+    // 64-bit little-endian ELF
+    if data.get(..6)? != b"\x7fELF\x02\x01" {
+        return None;
+    }
+
+    let section_offset = usize::try_from(read_u64(data, 0x28)?).ok()?;
+    let num_sections = read_u16(data, 0x3c)? as usize;
+    let section_names = read_u16(data, 0x3e)? as usize;
+
+    let sections = (0..num_sections)
+        .map(|index| {
+            let at = section_offset + index * SECTION_HEADER_SIZE;
+
+            Some(Section {
+                name: read_u32(data, at)?,
+                kind: read_u32(data, at + 4)?,
+                addr: read_u64(data, at + 16)?,
+                offset: read_u64(data, at + 24)?,
+                size: read_u64(data, at + 32)?,
+                link: read_u32(data, at + 40)?,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let shstrtab = sections.get(section_names)?;
+    let shstrtab = bytes(data, shstrtab.offset, shstrtab.size)?;
+
+    let mut hasher = DefaultHasher::new();
+    for section in &sections {
+        let name = shstrtab.get(section.name as usize..)?.split(|x| *x == 0).next()?;
+        if is_data(name) && section.kind != SHT_NOBITS {
+            name.hash(&mut hasher);
+            bytes(data, section.offset, section.size)?.hash(&mut hasher);
+        }
+    }
+    let data_hash = hasher.finish();
+
+    let symtab = sections.iter().find(|x| x.kind == SHT_SYMTAB)?;
+    let strtab = sections.get(symtab.link as usize)?;
+    let symbols = bytes(data, symtab.offset, symtab.size)?;
+    let names = bytes(data, strtab.offset, strtab.size)?;
+
+    let mut functions = HashMap::new();
+    for symbol in symbols.chunks_exact(SYMBOL_SIZE) {
+        let (info, index) = (symbol[4], read_u16(symbol, 6)? as usize);
+        let (value, size) = (read_u64(symbol, 8)?, read_u64(symbol, 16)?);
+
+        let section = match sections.get(index) {
+            Some(section) if info & 0xf == STT_FUNC && size > 0 && section.kind != SHT_NOBITS => section,
+            _ => continue,
+        };
+
+        let name = names.get(read_u32(symbol, 0)? as usize..)?;
+        let name = String::from_utf8_lossy(name.split(|x| *x == 0).next()?).into_owned();
+
+        let code = match value.checked_sub(section.addr).and_then(|x| bytes(data, section.offset + x, size)) {
+            Some(code) => code,
+            None => continue,
+        };
+
+        let mut hasher = DefaultHasher::new();
+        code.hash(&mut hasher);
+        functions.insert(name, hasher.finish());
+    }
+
+    Some(Symbols { functions, data: data_hash })
+}
@@ -0,0 +1,98 @@
+use crate::params::ParamSamples;
+
+/// Name of the pseudo-parameter sweeping over the size of the last level cache
+pub const LL_SIZE_PARAM: &str = "ll_size";
+
+/// Geometry of a single simulated cache
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CacheLevel {
+    /// Size in bytes
+    pub size: usize,
+    pub associativity: usize,
+    /// Size of a cache line in bytes
+    pub line_size: usize,
+}
+
+impl CacheLevel {
+    pub const fn new(size: usize, associativity: usize, line_size: usize) -> Self {
+        CacheLevel { size, associativity, line_size }
+    }
+
+    fn to_arg(self) -> String {
+        format!("{},{},{}", self.size, self.associativity, self.line_size)
+    }
+}
+
+/// Cache hierarchy simulated by cachegrind
+///
+/// Cachegrind requires the number of sets, `size / (associativity * line_size)`, to be a power of
+/// two.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CacheConfig {
+    pub i1: CacheLevel,
+    pub d1: CacheLevel,
+    pub ll: CacheLevel,
+}
+
+impl CacheConfig {
+    /// Small microcontroller-like caches with 16KiB L1 and 256KiB LL
+    pub const fn embedded() -> Self {
+        CacheConfig {
+            i1: CacheLevel::new(16384, 4, 32),
+            d1: CacheLevel::new(16384, 4, 32),
+            ll: CacheLevel::new(262144, 8, 32),
+        }
+    }
+
+    /// Typical desktop caches with 32KiB L1 and 8MiB LL
+    pub const fn desktop() -> Self {
+        CacheConfig {
+            i1: CacheLevel::new(32768, 8, 64),
+            d1: CacheLevel::new(32768, 8, 64),
+            ll: CacheLevel::new(8388608, 16, 64),
+        }
+    }
+
+    /// Server caches with 48KiB L1 data cache and 32MiB LL
+    pub const fn server() -> Self {
+        CacheConfig {
+            i1: CacheLevel::new(32768, 8, 64),
+            d1: CacheLevel::new(49152, 12, 64),
+            ll: CacheLevel::new(33554432, 16, 64),
+        }
+    }
+
+    pub fn from_preset(name: &str) -> Option<Self> {
+        match name {
+            "embedded" => Some(Self::embedded()),
+            "desktop" => Some(Self::desktop()),
+            "server" => Some(Self::server()),
+            _ => None,
+        }
+    }
+
+    /// Override the size of the last level cache if the parameters sweep over it
+    pub fn for_params(&self, params: &ParamSamples) -> Self {
+        let mut config = *self;
+        if let Some(size) = params.get_usize(LL_SIZE_PARAM) {
+            config.ll.size = size;
+        }
+
+        config
+    }
+
+    /// Arguments passed to cachegrind
+    pub fn to_args(&self) -> [String; 3] {
+        [
+            format!("--I1={}", self.i1.to_arg()),
+            format!("--D1={}", self.d1.to_arg()),
+            format!("--LL={}", self.ll.to_arg()),
+        ]
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self::desktop()
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use crate::child::{run_child, sample_name};
+use crate::error::{Error, Result};
+use crate::params::ParamSamples;
+use super::{bench_args, ValgrindConfig, cache::CacheConfig};
+
+/// Output file of benchmark `name` at `params`
+pub fn output_file(name: &str, params: &ParamSamples) -> PathBuf {
+    PathBuf::from(format!("target/alco/cachegrind.out.{}.{}", name, sample_name(params)))
+}
+
+pub fn run_bench(
+    config: &ValgrindConfig,
+    cache: &CacheConfig,
+    executable: &str,
+    i: usize,
+    params: &ParamSamples,
+    name: &str,
+    instr_at_start: bool,
+) -> Result<(CachegrindStats, Option<CachegrindStats>)> {
+    let output_file = output_file(name, params);
+    let old_file = PathBuf::from(format!("{}.old", output_file.display()));
+    std::fs::create_dir_all(output_file.parent().unwrap())?;
+
+    if output_file.exists() {
+        // Already run this benchmark once; move last results to .old
+        std::fs::copy(&output_file, &old_file)?;
+    }
+                                                                                           
+    let mut cmd = config.command("cachegrind");
+    // Set some reasonable cache sizes. The exact sizes matter less than having fixed s  izes,
+    // since otherwise cachegrind would take them from the CPU and make benchmark runs
+    // even more incomparable between machines.
+    cmd.args(cache.for_params(params).to_args());
+
+    if config.cache_sim {
+        // cache simulation is disabled by default since valgrind 3.21
+        cmd.arg("--cache-sim=yes");
+    }
+
+    if !instr_at_start {
+        // wait for the benchmark to call `start_instrumentation`
+        cmd.arg("--instr-at-start=no");
+    }
+
+    // function names are matched against the symbol table of the binary by the result cache
+    cmd.arg("--demangle=no");
+    cmd.arg(format!("--cachegrind-out-file={}", output_file.display()));
+
+    run_child(bench_args(&mut cmd, executable, i, params), "cachegrind", name, params)?;
+
+    let new_stats = parse_cachegrind_output(&output_file)?;
+    let old_stats = if old_file.exists() {
+        Some(parse_cachegrind_output(&old_file)?)
+    } else {
+        None
+    };
+  
+    Ok((new_stats, old_stats))
+}
+
+pub fn parse_cachegrind_output(file: &Path) -> Result<CachegrindStats> {
+    parse_cachegrind(BufReader::new(File::open(file)?))
+}
+
+fn parse_cachegrind<R: BufRead>(reader: R) -> Result<CachegrindStats> {
+    let mut events_line = None;
+    let mut summary_line = None;
+
+    for line in reader.lines() {
+        let line = line?;
+        if let Some(line) = line.strip_prefix("events: ") {
+            events_line = Some(line.trim().to_owned());
+        }
+        if let Some(line) = line.strip_prefix("summary: ") {
+            summary_line = Some(line.trim().to_owned());
+        }
+    }
+
+    match (events_line, summary_line) {
+        (Some(events), Some(summary)) => {
+            let values = summary.split_whitespace()
+                .map(|s| s.parse::<u64>())
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|_| parse_error("invalid summary line"))?;
+
+            let events: HashMap<_, _> = events
+                .split_whitespace()
+                .zip(values)
+                .collect();
+
+            // only instruction reads are always collected, the remaining events depend on
+            // whether cache simulation was enabled
+            Ok(CachegrindStats {
+                instruction_reads: *events.get("Ir").ok_or_else(|| parse_error("missing event Ir"))?,
+                instruction_l1_misses: events.get("I1mr").copied(),
+                instruction_cache_misses: events.get("ILmr").copied(),
+                data_reads: events.get("Dr").copied(),
+                data_l1_read_misses: events.get("D1mr").copied(),
+                data_cache_read_misses: events.get("DLmr").copied(),
+                data_writes: events.get("Dw").copied(),
+                data_l1_write_misses: events.get("D1mw").copied(),
+                data_cache_write_misses: events.get("DLmw").copied(),
+            })
+        }
+        _ => Err(parse_error("missing events or summary line")),
+    }
+}
+
+/// Names of all functions with costs in a cachegrind output file
+pub fn executed_functions(file: &Path) -> Result<Vec<String>> {
+    let mut functions = Vec::new();
+
+    for line in BufReader::new(File::open(file)?).lines() {
+        if let Some(name) = line?.strip_prefix("fn=") {
+            // newer versions may compress repeated names to `(id) name` or `(id)`
+            let name = match name.strip_prefix('(').and_then(|x| x.split_once(") ")) {
+                Some((_, name)) => name,
+                None => name,
+            };
+
+            if !name.starts_with('(') {
+                functions.push(name.to_string());
+            }
+        }
+    }
+
+    functions.sort();
+    functions.dedup();
+
+    Ok(functions)
+}
+
+fn parse_error(reason: &str) -> Error {
+    Error::ParseOutput("cachegrind".to_string(), reason.to_string())
+}
+
+impl CachegrindStats {
+    pub fn ram_accesses(&self) -> Option<u64> {
+        Some(self.instruction_cache_misses? + self.data_cache_read_misses? + self.data_cache_write_misses?)
+    }
+
+    /// Summarize cache hits, returns `None` if cachegrind ran without cache simulation
+    pub fn summarize(&self) -> Option<CachegrindSummary> {
+        let ram_hits = self.ram_accesses()?;
+        let l3_accesses =
+            self.instruction_l1_misses? + self.data_l1_read_misses? + self.data_l1_write_misses?;
+        let l3_hits = l3_accesses - ram_hits;
+
+        let total_memory_rw = self.instruction_reads + self.data_reads? + self.data_writes?;
+        let l1_hits = total_memory_rw - (ram_hits + l3_hits);
+
+        Some(CachegrindSummary {
+            l1_hits,
+            l3_hits,
+            ram_hits,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CachegrindStats {
+    pub instruction_reads: u64,
+    pub instruction_l1_misses: Option<u64>,
+    pub instruction_cache_misses: Option<u64>,
+    pub data_reads: Option<u64>,
+    pub data_l1_read_misses: Option<u64>,
+    pub data_cache_read_misses: Option<u64>,
+    pub data_writes: Option<u64>,
+    pub data_l1_write_misses: Option<u64>,
+    pub data_cache_write_misses: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CachegrindSummary {
+    pub l1_hits: u64,
+    pub l3_hits: u64,
+    pub ram_hits: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_instruction_reads_only() {
+        let output = "\
+desc: I1 cache: 32768 B, 64 B, 8-way associative
+cmd: target/debug/examples/sort --alco-run 0 false 0 n§usize§10
+events: Ir
+fl=src/main.rs
+fn=main
+1 120
+summary: 1200
+";
+        let stats = parse_cachegrind(output.as_bytes()).unwrap();
+
+        assert_eq!(stats.instruction_reads, 1200);
+        assert_eq!(stats.data_reads, None);
+        assert_eq!(stats.data_cache_read_misses, None);
+    }
+
+    #[test]
+    fn parses_cache_simulation() {
+        let output = "\
+events: Ir I1mr ILmr Dr D1mr DLmr Dw D1mw DLmw
+fl=src/main.rs
+fn=main
+1 120 3 2 40 5 1 20 4 0
+summary: 1200 30 20 400 50 10 200 40 5
+";
+        let stats = parse_cachegrind(output.as_bytes()).unwrap();
+
+        assert_eq!(stats.instruction_reads, 1200);
+        assert_eq!(stats.instruction_l1_misses, Some(30));
+        assert_eq!(stats.instruction_cache_misses, Some(20));
+        assert_eq!(stats.data_reads, Some(400));
+        assert_eq!(stats.data_l1_read_misses, Some(50));
+        assert_eq!(stats.data_cache_read_misses, Some(10));
+        assert_eq!(stats.data_writes, Some(200));
+        assert_eq!(stats.data_l1_write_misses, Some(40));
+        assert_eq!(stats.data_cache_write_misses, Some(5));
+    }
+
+    #[test]
+    fn rejects_missing_summary() {
+        assert!(parse_cachegrind("events: Ir\nfn=main\n1 120\n".as_bytes()).is_err());
+    }
+}
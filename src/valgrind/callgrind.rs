@@ -0,0 +1,210 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use crate::child::{run_child, sample_name};
+use crate::error::{Error, Result};
+use crate::params::ParamSamples;
+use super::{bench_args, ValgrindConfig};
+
+/// Instruction reads of a single run, broken down by function
+#[derive(Clone, Debug)]
+pub struct CallgrindStats {
+    pub instruction_reads: u64,
+    /// Inclusive instruction reads of each function, including its callees
+    pub functions: HashMap<String, u64>,
+    /// Instruction reads spent in each function itself, excluding its callees
+    pub self_costs: HashMap<String, u64>,
+}
+
+/// Returns the `num` functions with the highest self cost summed over all runs
+///
+/// The inclusive cost would rank the entry chain of every run first, e.g. `main` and the runner of
+/// the benchmark, which contain all other functions.
+pub fn hot_functions(stats: &[CallgrindStats], num: usize) -> Vec<String> {
+    let mut totals: HashMap<&str, u64> = HashMap::new();
+    for stat in stats {
+        for (name, cost) in &stat.self_costs {
+            *totals.entry(name).or_insert(0) += cost;
+        }
+    }
+
+    let mut functions = totals.into_iter().collect::<Vec<_>>();
+    functions.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    functions.into_iter()
+        .take(num)
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+pub fn run_callgrind(
+    config: &ValgrindConfig,
+    executable: &str,
+    i: usize,
+    params: &ParamSamples,
+    name: &str,
+    instr_at_start: bool,
+) -> Result<CallgrindStats> {
+    let output_file = PathBuf::from(format!("target/alco/callgrind.out.{}.{}", name, sample_name(params)));
+    std::fs::create_dir_all(output_file.parent().unwrap())?;
+
+    let mut cmd = config.command("callgrind");
+    // write function names in every line, which makes the output file easier to parse
+    cmd.arg("--compress-strings=no")
+        .arg("--compress-pos=no");
+
+    if !instr_at_start {
+        // wait for the benchmark to call `start_instrumentation`
+        cmd.arg("--instr-atstart=no");
+    }
+
+    cmd.arg(format!("--callgrind-out-file={}", output_file.display()));
+
+    run_child(bench_args(&mut cmd, executable, i, params), "callgrind", name, params)?;
+
+    parse_callgrind_output(&output_file)
+}
+
+/// Parse the instruction reads per function from a callgrind output file
+///
+/// Each cost line following `fn=` adds to the self cost of the function. The cost line following
+/// a `calls=` line is the inclusive cost of the call and adds to the inclusive cost of the caller.
+/// Recursive calls are skipped, as their cost is already contained in the self cost of the
+/// function.
+pub fn parse_callgrind_output(file: &Path) -> Result<CallgrindStats> {
+    parse_callgrind(BufReader::new(File::open(file)?))
+}
+
+fn parse_callgrind<R: BufRead>(reader: R) -> Result<CallgrindStats> {
+    let mut functions: HashMap<String, u64> = HashMap::new();
+    let mut self_costs: HashMap<String, u64> = HashMap::new();
+    let mut total = None;
+    let mut current_fn: Option<String> = None;
+    let mut called_fn: Option<String> = None;
+    let mut after_calls = false;
+
+    for line in reader.lines() {
+        let line = line?;
+
+        if let Some(name) = line.strip_prefix("fn=") {
+            current_fn = Some(name.trim().to_owned());
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("cfn=") {
+            called_fn = Some(name.trim().to_owned());
+            continue;
+        }
+        if line.starts_with("calls=") {
+            after_calls = true;
+            continue;
+        }
+        if let Some(line) = line.strip_prefix("summary: ").or_else(|| line.strip_prefix("totals: ")) {
+            let value = line.split_whitespace().next()
+                .and_then(|x| x.parse::<u64>().ok())
+                .ok_or_else(|| parse_error("invalid summary line"))?;
+            total = total.or(Some(value));
+            continue;
+        }
+
+        // cost lines start with a position, which is a number or a relative offset
+        let is_cost = line.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-' || c == '*');
+        if !is_cost {
+            continue;
+        }
+
+        let cost = match line.split_whitespace().nth(1) {
+            Some(cost) => cost.parse::<u64>().map_err(|_| parse_error("invalid cost line"))?,
+            None => 0,
+        };
+
+        let current = match &current_fn {
+            Some(name) => name,
+            None => continue,
+        };
+
+        if after_calls {
+            after_calls = false;
+            if called_fn.as_ref() == Some(current) {
+                continue;
+            }
+        } else {
+            *self_costs.entry(current.clone()).or_insert(0) += cost;
+        }
+
+        *functions.entry(current.clone()).or_insert(0) += cost;
+    }
+
+    let instruction_reads = total.ok_or_else(|| parse_error("missing summary line"))?;
+
+    Ok(CallgrindStats {
+        instruction_reads,
+        functions,
+        self_costs,
+    })
+}
+
+fn parse_error(reason: &str) -> Error {
+    Error::ParseOutput("callgrind".to_string(), reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `work` calls itself once and `helper` once, the recursive call is part of its self cost
+    const OUTPUT: &str = "\
+version: 1
+creator: callgrind-3.19.0
+events: Ir
+summary: 1000
+
+fn=main
+1 10
+cfn=work
+calls=1 2
+2 990
+
+fn=work
+5 400
+cfn=work
+calls=3 5
+6 300
+cfn=helper
+calls=10 8
+7 590
+
+fn=helper
+9 590
+
+totals: 1000
+";
+
+    #[test]
+    fn parses_inclusive_and_self_costs() {
+        let stats = parse_callgrind(OUTPUT.as_bytes()).unwrap();
+
+        assert_eq!(stats.instruction_reads, 1000);
+        assert_eq!(stats.functions["main"], 1000);
+        assert_eq!(stats.functions["work"], 990);
+        assert_eq!(stats.functions["helper"], 590);
+        assert_eq!(stats.self_costs["main"], 10);
+        assert_eq!(stats.self_costs["work"], 400);
+        assert_eq!(stats.self_costs["helper"], 590);
+    }
+
+    #[test]
+    fn ranks_functions_by_self_cost() {
+        let stats = parse_callgrind(OUTPUT.as_bytes()).unwrap();
+
+        assert_eq!(hot_functions(&[stats.clone(), stats], 2), vec!["helper", "work"]);
+    }
+
+    #[test]
+    fn rejects_missing_summary() {
+        assert!(parse_callgrind("fn=main\n1 10\n".as_bytes()).is_err());
+    }
+}
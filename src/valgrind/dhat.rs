@@ -0,0 +1,92 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use crate::child::{run_child, sample_name};
+use crate::error::{Error, Result};
+use crate::params::ParamSamples;
+use super::{bench_args, ValgrindConfig};
+
+/// Heap statistics of a single run
+#[derive(Clone, Debug)]
+pub struct DhatStats {
+    /// Bytes allocated over the whole run
+    pub total_bytes: u64,
+    /// Number of allocations over the whole run
+    pub total_blocks: u64,
+    /// Bytes alive at the point of maximal heap size
+    pub peak_bytes: u64,
+}
+
+pub fn run_dhat(
+    config: &ValgrindConfig,
+    executable: &str,
+    i: usize,
+    params: &ParamSamples,
+    name: &str,
+) -> Result<DhatStats> {
+    let output_file = PathBuf::from(format!("target/alco/dhat.out.{}.{}", name, sample_name(params)));
+    let log_file = output_file.with_file_name(format!("dhat.log.{}.{}", name, sample_name(params)));
+    std::fs::create_dir_all(output_file.parent().unwrap())?;
+
+    let mut cmd = config.command("dhat");
+    // the summary is written to the log, the profile itself is only kept for inspection
+    cmd.arg(format!("--dhat-out-file={}", output_file.display()))
+        .arg(format!("--log-file={}", log_file.display()));
+
+    run_child(bench_args(&mut cmd, executable, i, params), "dhat", name, params)?;
+
+    parse_dhat_log(&log_file)
+}
+
+/// Parse the number of bytes and blocks from a summary line, e.g. `1,024 bytes in 3 blocks`
+fn parse_bytes_blocks(line: &str) -> Option<(u64, u64)> {
+    let parts = line.split_whitespace().collect::<Vec<_>>();
+    match parts.as_slice() {
+        [bytes, "bytes", "in", blocks, "blocks", ..] => {
+            let bytes = bytes.replace(',', "").parse().ok()?;
+            let blocks = blocks.replace(',', "").parse().ok()?;
+
+            Some((bytes, blocks))
+        }
+        _ => None,
+    }
+}
+
+/// Parse the heap summary printed by dhat at exit
+///
+/// The relevant lines look like
+/// ```text
+/// ==1234== Total:     1,024 bytes in 3 blocks
+/// ==1234== At t-gmax: 512 bytes in 1 blocks
+/// ```
+pub fn parse_dhat_log(file: &Path) -> Result<DhatStats> {
+    let mut total = None;
+    let mut peak = None;
+
+    let file_in = File::open(file)?;
+
+    for line in BufReader::new(file_in).lines() {
+        let line = line?;
+        // strip the `==pid==` prefix
+        let line = line.splitn(3, "==").nth(2).unwrap_or(&line).trim();
+
+        if let Some(line) = line.strip_prefix("Total:") {
+            total = parse_bytes_blocks(line);
+        }
+        if let Some(line) = line.strip_prefix("At t-gmax:") {
+            peak = parse_bytes_blocks(line);
+        }
+    }
+
+    match (total, peak) {
+        (Some((total_bytes, total_blocks)), Some((peak_bytes, _))) => Ok(DhatStats {
+            total_bytes,
+            total_blocks,
+            peak_bytes,
+        }),
+        _ => Err(Error::ParseOutput("dhat".to_string(), "missing heap summary".to_string())),
+    }
+}
@@ -0,0 +1,49 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use crate::child::{run_child, sample_name};
+use crate::error::{Error, Result};
+use crate::params::ParamSamples;
+use super::{bench_args, ValgrindConfig};
+
+/// Run benchmark `i` under massif and return the peak heap size in bytes
+pub fn run_massif(
+    config: &ValgrindConfig,
+    executable: &str,
+    i: usize,
+    params: &ParamSamples,
+    name: &str,
+) -> Result<u64> {
+    let output_file = PathBuf::from(format!("target/alco/massif.out.{}.{}", name, sample_name(params)));
+    std::fs::create_dir_all(output_file.parent().unwrap())?;
+
+    let mut cmd = config.command("massif");
+    // take a snapshot at every new peak, otherwise the peak may be missed by up to 1%
+    cmd.arg("--peak-inaccuracy=0.0")
+        .arg(format!("--massif-out-file={}", output_file.display()));
+
+    run_child(bench_args(&mut cmd, executable, i, params), "massif", name, params)?;
+
+    parse_massif_output(&output_file)
+}
+
+/// Parse the largest heap size of all snapshots in a massif output file
+pub fn parse_massif_output(file: &Path) -> Result<u64> {
+    let file_in = File::open(file)?;
+    let mut peak = 0;
+
+    for line in BufReader::new(file_in).lines() {
+        let line = line?;
+        if let Some(heap) = line.strip_prefix("mem_heap_B=") {
+            let heap = heap.trim().parse::<u64>()
+                .map_err(|_| Error::ParseOutput("massif".to_string(), "invalid snapshot".to_string()))?;
+
+            peak = u64::max(peak, heap);
+        }
+    }
+
+    Ok(peak)
+}
@@ -0,0 +1,185 @@
+//! Helpers to run benchmarks under the tools of valgrind
+
+pub mod cache;
+pub mod cachegrind;
+pub mod callgrind;
+pub mod dhat;
+pub mod massif;
+
+use std::process::{Command, Stdio};
+
+use crate::child::child_args;
+use crate::error::{Error, Result};
+use crate::params::ParamSamples;
+
+pub fn check_valgrind() -> bool {
+    let result = Command::new("valgrind")
+        .arg("--tool=cachegrind")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+
+    match result {
+        Err(e) => {
+            println!("Unexpected error while launching valgrind. Error: {}", e);
+            false
+        }
+        Ok(status) => {
+            if status.success() {
+                true
+            } else {
+                println!("Failed to launch valgrind. Error: {}. Please ensure that valgrind   is installed and on the $PATH.", status);
+                false
+            }
+        }
+    }
+}
+
+/// Returns the version string of the installed valgrind, e.g. `valgrind-3.21.0`
+fn get_valgrind_version() -> Option<String> {
+    let output = Command::new("valgrind")
+        .arg("--version")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    Some(String::from_utf8(output.stdout).ok()?.trim().to_string())
+}
+
+/// Parses the `(major, minor)` version from a version string
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.strip_prefix("valgrind-")?.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+
+    Some((major, minor))
+}
+
+fn get_arch() -> Result<String> {
+    let output = Command::new("uname")
+        .arg("-m")
+        .stdout(Stdio::piped())
+        .output()
+        .map_err(|e| Error::Launch("uname".to_string(), e))?;
+
+    let arch = String::from_utf8(output.stdout)
+        .map_err(|_| Error::ParseOutput("uname".to_string(), "invalid unicode".to_string()))?;
+
+    Ok(arch.trim().to_owned())
+}
+
+fn basic_valgrind() -> Command {
+    Command::new("valgrind")
+}
+
+#[cfg(target_os = "linux")]
+fn check_setarch(arch: &str) -> bool {
+    Command::new("setarch")
+        .arg(arch)
+        .arg("-R")
+        .arg("true")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_setarch(_arch: &str) -> bool {
+    false
+}
+
+#[cfg(target_os = "linux")]
+fn valgrind_without_aslr(arch: &str) -> Command {
+    let mut cmd = Command::new("setarch");
+    cmd.arg(arch)
+        .arg("-R")
+        .arg("valgrind");
+    cmd
+}
+
+#[cfg(not(target_os = "linux"))]
+fn valgrind_without_aslr(_arch: &str) -> Command {
+    // ASLR can only be disabled with `setarch` on linux, fall back to plain valgrind
+    basic_valgrind()
+}
+
+/// Settings of the valgrind installation shared by all benchmark runs
+#[derive(Clone, Debug)]
+pub struct ValgrindConfig {
+    /// Version string, part of the keys of cached results since instruction counts differ between
+    /// versions
+    pub version: Option<String>,
+    pub arch: String,
+    pub allow_aslr: bool,
+    pub cache_sim: bool,
+    pub client_requests: bool,
+    pub dhat: bool,
+}
+
+impl ValgrindConfig {
+    /// Probe the valgrind installation, returns `None` if valgrind is not available
+    pub fn detect() -> Result<Option<Self>> {
+        if !check_valgrind() {
+            return Ok(None);
+        }
+
+        let arch = get_arch()?;
+
+        // request cache simulation explicitly, `--cache-sim` is available since valgrind 3.5 and
+        // cachegrind understands client requests since valgrind 3.22. DHAT is not experimental
+        // anymore since valgrind 3.15
+        let version = get_valgrind_version();
+        let (cache_sim, client_requests, dhat) = match version.as_deref().and_then(parse_version) {
+            Some(version) => (version >= (3, 5), version >= (3, 22), version >= (3, 15)),
+            None => {
+                println!("Could not determine valgrind version, cache statistics may be unavailable.");
+                (false, false, false)
+            }
+        };
+
+        // disable ASLR for reproducible instruction counts, unless explicitly allowed or not
+        // supported on this platform
+        let allow_aslr = if std::env::var_os("ALCO_ALLOW_ASLR").is_some() {
+            true
+        } else if !check_setarch(&arch) {
+            println!("Could not disable ASLR with `setarch {} -R`, instruction counts may vary between runs. Set ALCO_ALLOW_ASLR to silence this warning.", arch);
+            true
+        } else {
+            false
+        };
+
+        Ok(Some(ValgrindConfig {
+            version,
+            arch,
+            allow_aslr,
+            cache_sim,
+            client_requests,
+            dhat,
+        }))
+    }
+
+    /// Create a valgrind command running `tool`, wrapped in `setarch` if ASLR is disabled
+    pub fn command(&self, tool: &str) -> Command {
+        let mut cmd = if self.allow_aslr {
+            basic_valgrind()
+        } else {
+            valgrind_without_aslr(&self.arch)
+        };
+
+        cmd.arg(format!("--tool={}", tool))
+            .env("ALCO_TOOL", tool);
+
+        cmd
+    }
+}
+
+/// Append the executable and arguments to run benchmark `i` with `params` in valgrind
+pub fn bench_args<'a>(cmd: &'a mut Command, executable: &str, i: usize, params: &ParamSamples) -> &'a mut Command {
+    cmd.arg(executable);
+
+    child_args(cmd, i, params)
+}
@@ -0,0 +1,116 @@
+//! In-process wall-clock measurements for environments without valgrind
+//!
+//! Every sample point is repeated until the standard error of the median settles, similar to
+//! criterion. If the benchmark calls `start_instrumentation` and `stop_instrumentation`, only the
+//! time between these calls is measured.
+
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+use crate::metric::{Metric, Stats};
+use crate::params::ParamSamples;
+
+/// Minimal number of repetitions of a sample point
+const MIN_ITERATIONS: usize = 5;
+/// Maximal number of repetitions of a sample point
+const MAX_ITERATIONS: usize = 1000;
+/// Stop repeating a sample point after this time, even if the median is not settled
+const MAX_DURATION: Duration = Duration::from_secs(2);
+/// Target relative standard error of the median
+const TARGET_ERROR: f64 = 0.01;
+
+thread_local! {
+    static REGION_START: Cell<Option<Instant>> = const { Cell::new(None) };
+    static REGION: Cell<Option<Duration>> = const { Cell::new(None) };
+}
+
+/// Start the timer of a measured region
+pub fn start() {
+    REGION_START.with(|x| x.set(Some(Instant::now())));
+}
+
+/// Stop the timer of a measured region and add the elapsed time to the region
+pub fn stop() {
+    let now = Instant::now();
+    if let Some(start) = REGION_START.with(|x| x.take()) {
+        REGION.with(|x| x.set(Some(x.get().unwrap_or_default() + (now - start))));
+    }
+}
+
+fn run_once(func: fn(ParamSamples), params: &ParamSamples) -> f64 {
+    let params = params.clone();
+
+    REGION_START.with(|x| x.set(None));
+    REGION.with(|x| x.set(None));
+
+    let start = Instant::now();
+    func(params);
+    let elapsed = start.elapsed();
+
+    // prefer the measured region if the benchmark uses manual instrumentation
+    REGION.with(|x| x.get())
+        .unwrap_or(elapsed)
+        .as_nanos() as f64
+}
+
+/// Returns median and median absolute deviation
+pub fn median_mad(mut times: Vec<f64>) -> (f64, f64) {
+    let median = |x: &mut Vec<f64>| {
+        x.sort_by(|a, b| a.total_cmp(b));
+        let n = x.len();
+        if n.is_multiple_of(2) {
+            (x[n / 2 - 1] + x[n / 2]) / 2.0
+        } else {
+            x[n / 2]
+        }
+    };
+
+    let med = median(&mut times);
+    let mut deviations = times.iter().map(|x| (x - med).abs()).collect();
+    let mad = median(&mut deviations);
+
+    (med, mad)
+}
+
+/// Variance of the median, estimated from the median absolute deviation of `n` samples
+pub fn median_variance(mad: f64, n: usize) -> f64 {
+    let sigma = 1.4826 * mad;
+
+    std::f64::consts::FRAC_PI_2 * sigma * sigma / n as f64
+}
+
+/// Measure the median wall-clock time of `func` with `params`
+pub fn measure_wallclock(func: fn(ParamSamples), params: &ParamSamples) -> Stats {
+    // warm up caches and the allocator
+    run_once(func, params);
+
+    let start = Instant::now();
+    let mut times = Vec::new();
+
+    loop {
+        times.push(run_once(func, params));
+
+        if times.len() >= MAX_ITERATIONS {
+            break;
+        }
+
+        if times.len() >= MIN_ITERATIONS {
+            let (median, mad) = median_mad(times.clone());
+            let error = median_variance(mad, times.len()).sqrt() / median.max(1.0);
+
+            if error < TARGET_ERROR || start.elapsed() > MAX_DURATION {
+                break;
+            }
+        }
+    }
+
+    let n = times.len();
+    let (median, mad) = median_mad(times);
+
+    let mut stats = Stats::default();
+    stats.insert_noisy(Metric::WallTime, median as u64, median_variance(mad, n));
+
+    stats
+}